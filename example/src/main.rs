@@ -12,7 +12,10 @@ fn print(s: &str) {
 fn main() {
     print("Please enter your name: ");
 
-    let name = readln!();
+    let name = match readln!() {
+        Some(name) => name,
+        None => return,
+    };
     let year;
     let place;
     loop {
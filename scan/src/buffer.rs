@@ -0,0 +1,76 @@
+use std::io;
+use std::iter::{repeat};
+
+use error::{ReadError};
+
+/// The buffer size used by sources unless another one is requested.
+pub static DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Bytes that have been read in bulk but have not yet been handed out.
+pub struct Buffer {
+    data: Vec<u8>,
+    pos: usize,
+    end: usize,
+}
+
+impl Buffer {
+    pub fn new() -> Buffer {
+        Buffer {
+            data: vec!(),
+            pos: 0,
+            end: 0,
+        }
+    }
+
+    /// Returns the next buffered byte.
+    pub fn next(&mut self) -> Option<u8> {
+        if self.pos < self.end {
+            self.pos += 1;
+            Some(self.data[self.pos - 1])
+        } else {
+            None
+        }
+    }
+
+    /// Replaces the buffered bytes by at most `n` new bytes.
+    ///
+    /// `read` is called with a slice of length `n` and returns how many bytes it has
+    /// stored in it. Interrupted calls are retried.
+    pub fn fill<F>(&mut self, n: usize, mut read: F) -> Result<(), ReadError>
+        where F: FnMut(&mut [u8]) -> io::Result<usize>,
+    {
+        let len = self.data.len();
+        if len < n {
+            self.data.extend(repeat(0).take(n - len));
+        }
+        self.pos = 0;
+        self.end = 0;
+        loop {
+            match read(&mut self.data[..n]) {
+                Ok(0) => return Err(ReadError::EndOfInput),
+                Ok(len) => {
+                    self.end = len;
+                    return Ok(());
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => { },
+                Err(e) => return Err(ReadError::Io(e)),
+            }
+        }
+    }
+
+    /// Puts `bytes` in front of the buffered bytes.
+    pub fn restore(&mut self, bytes: &[u8]) {
+        if bytes.len() <= self.pos {
+            self.pos -= bytes.len();
+            for (i, &b) in bytes.iter().enumerate() {
+                self.data[self.pos + i] = b;
+            }
+        } else {
+            let mut data = bytes.to_vec();
+            data.push_all(&self.data[self.pos..self.end]);
+            self.pos = 0;
+            self.end = data.len();
+            self.data = data;
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use std::any::{Any};
+use std::error::{Error};
+use std::fmt;
+use std::io;
+
+use position::{Position};
+
+/// The reason why no byte could be read.
+#[derive(Debug)]
+pub enum ReadError {
+    /// The end of the current line has been reached.
+    EndOfLine,
+    /// The end of the input has been reached.
+    EndOfInput,
+    /// The source returned an error.
+    Io(io::Error),
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::EndOfLine => write!(f, "end of line"),
+            ReadError::EndOfInput => write!(f, "end of input"),
+            ReadError::Io(ref e) => write!(f, "I/O error: {}", e),
+        }
+    }
+}
+
+impl Error for ReadError {
+    fn description(&self) -> &str {
+        match *self {
+            ReadError::EndOfLine => "end of line",
+            ReadError::EndOfInput => "end of input",
+            ReadError::Io(ref e) => e.description(),
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            ReadError::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// What a specifier expected to find in the input.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expected {
+    /// The literal text.
+    Literal(String),
+    /// An integer.
+    Integer,
+    /// A floating point number.
+    Float,
+    /// A character.
+    Character,
+    /// `true` or `false`.
+    Boolean,
+    /// An integer that fits into the named type.
+    Range(&'static str),
+    /// A character of the character class, e.g., `[a-z]`.
+    Class(String),
+    /// A description of the value, e.g., `a point`.
+    Custom(String),
+    /// A value of the named type that is parsed with `FromStr`.
+    Value(String),
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Expected::Literal(ref s) => write!(f, "`{}`", s),
+            Expected::Integer => write!(f, "an integer"),
+            Expected::Float => write!(f, "a float"),
+            Expected::Character => write!(f, "a character"),
+            Expected::Boolean => write!(f, "a boolean"),
+            Expected::Range(ty) => write!(f, "an integer in the range of {}", ty),
+            Expected::Class(ref c) => write!(f, "a character in `{}`", c),
+            Expected::Custom(ref s) => write!(f, "{}", s),
+            Expected::Value(ref ty) => write!(f, "a value of type `{}`", ty),
+        }
+    }
+}
+
+/// The reason why a number couldn't be parsed.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NumError {
+    /// The input doesn't start with a number.
+    Invalid,
+    /// The number doesn't fit into the requested type.
+    Overflow,
+}
+
+/// The error returned by `FromStr` when a value couldn't be parsed.
+pub struct ParseError {
+    error: Box<Any + Send>,
+    description: String,
+}
+
+impl ParseError {
+    /// Wraps the error `e`.
+    pub fn new<E: Any + Send + fmt::Debug>(e: E) -> ParseError {
+        ParseError {
+            description: format!("{:?}", e),
+            error: Box::new(e),
+        }
+    }
+
+    /// Returns a reference to the original error if it has type `E`.
+    pub fn downcast_ref<E: Any>(&self) -> Option<&E> {
+        let e: &Any = &*self.error;
+        e.downcast_ref()
+    }
+
+    /// Returns the original error.
+    pub fn into_inner(self) -> Box<Any + Send> {
+        self.error
+    }
+}
+
+impl fmt::Debug for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ParseError({})", self.description)
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description)
+    }
+}
+
+/// The error returned by `try_scan!` and `try_scanln!`.
+#[derive(Debug)]
+pub struct ScanError {
+    /// The index of the specifier that failed.
+    ///
+    /// Every specifier counts, including literals and whitespace. Adjacent literal
+    /// characters form one specifier.
+    pub index: usize,
+    /// What the specifier expected.
+    pub expected: Expected,
+    /// The byte that was found instead or the reason why no byte could be read.
+    pub found: Result<u8, ReadError>,
+    /// The position of the byte that was found.
+    pub position: Position,
+    /// The error returned by `FromStr` if the specifier used it.
+    pub parse_error: Option<ParseError>,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        try!(write!(f, "{}: expected {}, found ", self.position, self.expected));
+        try!(match self.found {
+            Ok(b) if b >= 0x20 && b < 0x7F => write!(f, "`{}`", b as char),
+            Ok(b) => write!(f, "byte 0x{:02X}", b),
+            Err(ref e) => write!(f, "{}", e),
+        });
+        match self.parse_error {
+            Some(ref e) => write!(f, " ({})", e),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Error for ScanError {
+    fn description(&self) -> &str {
+        "input didn't match the specification"
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match self.found {
+            Err(ref e @ ReadError::Io(..)) => Some(e),
+            _ => None,
+        }
+    }
+}
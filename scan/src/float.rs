@@ -0,0 +1,161 @@
+use std::num::{Int, Float};
+
+/// A floating point number that has been read but not yet rounded.
+pub enum Number {
+    Finite(Parts),
+    /// Infinity with the given sign.
+    Infinite(bool),
+    NaN,
+}
+
+impl Number {
+    pub fn to_f64(&self) -> Option<f64> {
+        match *self {
+            Number::Finite(ref p) => p.to_f64(),
+            Number::Infinite(negative) => Some(if negative {
+                Float::neg_infinity()
+            } else {
+                Float::infinity()
+            }),
+            Number::NaN => Some(Float::nan()),
+        }
+    }
+
+    pub fn to_f32(&self) -> Option<f32> {
+        match *self {
+            Number::Finite(ref p) => p.to_f32(),
+            Number::Infinite(negative) => Some(if negative {
+                Float::neg_infinity()
+            } else {
+                Float::infinity()
+            }),
+            Number::NaN => Some(Float::nan()),
+        }
+    }
+}
+
+/// The sign, digits and exponent of a finite floating point number.
+pub struct Parts {
+    pub negative: bool,
+    /// 10 or a power of two.
+    pub radix: u32,
+    /// The values of the digits before and after the point.
+    pub digits: Vec<u8>,
+    /// The number of digits before the point.
+    pub int_digits: usize,
+    /// The exponent to base 10 for decimal numbers, to base 2 otherwise.
+    pub exp: i64,
+}
+
+impl Parts {
+    pub fn to_f64(&self) -> Option<f64> {
+        let v = match self.radix {
+            10 => match self.decimal().parse::<f64>() {
+                Ok(v) => v,
+                _ => return None,
+            },
+            _ => self.binary(53, -1022),
+        };
+        Some(if self.negative { -v } else { v })
+    }
+
+    pub fn to_f32(&self) -> Option<f32> {
+        let v = match self.radix {
+            10 => match self.decimal().parse::<f32>() {
+                Ok(v) => v,
+                _ => return None,
+            },
+            // The result has been rounded to the precision of an f32 so the conversion
+            // is exact.
+            _ => self.binary(24, -126) as f32,
+        };
+        Some(if self.negative { -v } else { v })
+    }
+
+    /// Formats the digits and the exponent as a decimal number without sign.
+    fn decimal(&self) -> String {
+        let mut s = String::with_capacity(self.digits.len() + 1);
+        for (i, &d) in self.digits.iter().enumerate() {
+            if i == self.int_digits {
+                s.push('.');
+            }
+            s.push((b'0' + d) as char);
+        }
+        if self.exp != 0 {
+            s.push_str(&format!("e{}", self.exp)[]);
+        }
+        s
+    }
+
+    /// Converts digits in a base that is a power of two to the nearest float with
+    /// `bits` bits of precision and minimal normal exponent `min_exp`.
+    fn binary(&self, bits: i64, min_exp: i64) -> f64 {
+        let b = self.radix.trailing_zeros() as i64;
+        let mut m = 0u64;
+        let mut exp = 0i64;
+        let mut sticky = false;
+        for (i, &d) in self.digits.iter().enumerate() {
+            let frac = i >= self.int_digits;
+            if m >> (64 - b) as usize == 0 {
+                m = m << b as usize | d as u64;
+                if frac {
+                    exp -= b;
+                }
+            } else {
+                sticky |= d != 0;
+                if !frac {
+                    exp += b;
+                }
+            }
+        }
+        round(m, exp + self.exp, sticky, bits, min_exp)
+    }
+}
+
+/// Rounds `m * 2^exp` to the nearest float with `bits` bits of precision, ties to even.
+///
+/// Below `2^min_exp` the precision decreases like that of subnormal numbers. `sticky`
+/// says if non-zero bits below `m` have been dropped.
+fn round(m: u64, exp: i64, sticky: bool, bits: i64, min_exp: i64) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+    let lz = m.leading_zeros() as i64;
+    let m = m << lz as usize;
+    let exp = exp - lz;
+    // The most significant bit of the value.
+    let lead = exp + 63;
+    let p = if lead < min_exp { bits - (min_exp - lead) } else { bits };
+    if p < 0 {
+        return 0.0;
+    }
+    if p == 0 {
+        // The value is at least half of the smallest positive number.
+        return match m == 1 << 63 && !sticky {
+            true => 0.0,
+            false => ldexp(1.0, lead + 1),
+        };
+    }
+    let shift = 64 - p;
+    let kept = m >> shift as usize;
+    let rem = m & ((1 << shift as usize) - 1);
+    let half = 1 << (shift - 1) as usize;
+    let up = rem > half || (rem == half && (sticky || kept & 1 == 1));
+    ldexp((kept + up as u64) as f64, exp + shift)
+}
+
+/// Computes `x * 2^e`.
+///
+/// This is exact if the result is representable.
+fn ldexp(mut x: f64, e: i64) -> f64 {
+    let mut e = if e > 2200 { 2200 } else if e < -2200 { -2200 } else { e };
+    while e > 1000 {
+        x *= 2.0.powi(1000);
+        e -= 1000;
+    }
+    while e < -1000 {
+        x *= 2.0.powi(-1000);
+        e += 1000;
+    }
+    x * 2.0.powi(e as i32)
+}
@@ -0,0 +1,113 @@
+#[cfg(feature = "big")] use num::bigint::{BigInt};
+
+use std::num::{Int};
+
+use error::{Expected, NumError};
+use int128::{U128, I128};
+use source::{Source};
+use super::{Scanner};
+
+/// Types that can be parsed by a `Scanner`.
+///
+/// Every type that implements this trait can be used in the specifiers of the macros,
+/// e.g., `{geometry::Point}`.
+pub trait FromScan {
+    /// Parses a value or returns what was expected instead.
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<Self, Expected>;
+}
+
+macro_rules! signed {
+    ($($ty:ident)*) => {$(
+        impl FromScan for $ty {
+            fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<$ty, Expected> {
+                let min: $ty = Int::min_value();
+                let max: $ty = Int::max_value();
+                match scanner.signed_integer() {
+                    Ok(v) if v >= min as i64 && v <= max as i64 => Ok(v as $ty),
+                    Ok(_) | Err(NumError::Overflow) => {
+                        Err(Expected::Range(stringify!($ty)))
+                    },
+                    Err(NumError::Invalid) => Err(Expected::Integer),
+                }
+            }
+        }
+    )*}
+}
+
+macro_rules! unsigned {
+    ($($ty:ident)*) => {$(
+        impl FromScan for $ty {
+            fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<$ty, Expected> {
+                let max: $ty = Int::max_value();
+                match scanner.unsigned_integer() {
+                    Ok(v) if v <= max as u64 => Ok(v as $ty),
+                    Ok(_) | Err(NumError::Overflow) => {
+                        Err(Expected::Range(stringify!($ty)))
+                    },
+                    Err(NumError::Invalid) => Err(Expected::Integer),
+                }
+            }
+        }
+    )*}
+}
+
+signed!(i8 i16 i32 i64 isize);
+unsigned!(u8 u16 u32 u64 usize);
+
+impl FromScan for I128 {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<I128, Expected> {
+        match scanner.signed_integer128() {
+            Ok(v) => Ok(v),
+            Err(NumError::Overflow) => Err(Expected::Range("i128")),
+            Err(NumError::Invalid) => Err(Expected::Integer),
+        }
+    }
+}
+
+impl FromScan for U128 {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<U128, Expected> {
+        match scanner.unsigned_integer128() {
+            Ok(v) => Ok(v),
+            Err(NumError::Overflow) => Err(Expected::Range("u128")),
+            Err(NumError::Invalid) => Err(Expected::Integer),
+        }
+    }
+}
+
+impl FromScan for f32 {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<f32, Expected> {
+        scanner.float32().ok_or(Expected::Float)
+    }
+}
+
+impl FromScan for f64 {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<f64, Expected> {
+        scanner.float().ok_or(Expected::Float)
+    }
+}
+
+/// Reads a word.
+impl FromScan for String {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<String, Expected> {
+        Ok(scanner.word())
+    }
+}
+
+impl FromScan for char {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<char, Expected> {
+        scanner.character().ok_or(Expected::Character)
+    }
+}
+
+impl FromScan for bool {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<bool, Expected> {
+        scanner.boolean().ok_or(Expected::Boolean)
+    }
+}
+
+#[cfg(feature = "big")]
+impl FromScan for BigInt {
+    fn scan<S: Source>(scanner: &mut Scanner<S>) -> Result<BigInt, Expected> {
+        scanner.big_integer().map_err(|_| Expected::Integer)
+    }
+}
@@ -0,0 +1,163 @@
+use std::fmt;
+use std::{i64, u64};
+use std::num::{Int};
+
+/// An unsigned 128-bit integer.
+///
+/// Returned by `{u128}` and `Scanner::unsigned_integer128`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct U128 {
+    /// The upper 64 bits.
+    pub hi: u64,
+    /// The lower 64 bits.
+    pub lo: u64,
+}
+
+/// A signed 128-bit integer in two's complement.
+///
+/// Returned by `{i128}` and `Scanner::signed_integer128`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct I128 {
+    /// The upper 64 bits including the sign.
+    pub hi: i64,
+    /// The lower 64 bits.
+    pub lo: u64,
+}
+
+impl U128 {
+    pub fn zero() -> U128 {
+        U128 { hi: 0, lo: 0 }
+    }
+
+    pub fn from_u64(v: u64) -> U128 {
+        U128 { hi: 0, lo: v }
+    }
+
+    /// Returns the value if it fits into a `u64`.
+    pub fn to_u64(self) -> Option<u64> {
+        match self.hi {
+            0 => Some(self.lo),
+            _ => None,
+        }
+    }
+
+    /// Multiplies by `m` or returns `None` on overflow.
+    pub fn checked_mul(self, m: u64) -> Option<U128> {
+        let (carry, lo) = mul_wide(self.lo, m);
+        let (over, hi) = mul_wide(self.hi, m);
+        if over != 0 {
+            return None;
+        }
+        hi.checked_add(carry).map(|hi| U128 { hi: hi, lo: lo })
+    }
+
+    /// Adds `a` or returns `None` on overflow.
+    pub fn checked_add(self, a: u64) -> Option<U128> {
+        match self.lo.checked_add(a) {
+            Some(lo) => Some(U128 { hi: self.hi, lo: lo }),
+            None => self.hi.checked_add(1).map(|hi| {
+                U128 { hi: hi, lo: a - (u64::MAX - self.lo) - 1 }
+            }),
+        }
+    }
+
+    /// Divides by `d`, which must be less than `2^32`, and returns the quotient and the
+    /// remainder.
+    fn div_rem(self, d: u64) -> (U128, u64) {
+        let mut words = [self.hi >> 32, self.hi & 0xFFFF_FFFF,
+                         self.lo >> 32, self.lo & 0xFFFF_FFFF];
+        let mut rem = 0;
+        for w in words.iter_mut() {
+            let cur = rem << 32 | *w;
+            *w = cur / d;
+            rem = cur % d;
+        }
+        let q = U128 {
+            hi: words[0] << 32 | words[1],
+            lo: words[2] << 32 | words[3],
+        };
+        (q, rem)
+    }
+
+    /// Returns the two's complement of the value.
+    fn negate(self) -> U128 {
+        match self.lo {
+            // !hi + 1 only overflows if the value is zero.
+            0 if self.hi == 0 => self,
+            0 => U128 { hi: !self.hi + 1, lo: 0 },
+            _ => U128 { hi: !self.hi, lo: !self.lo + 1 },
+        }
+    }
+}
+
+impl fmt::Display for U128 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut digits = vec!();
+        let mut v = *self;
+        loop {
+            let (q, r) = v.div_rem(10);
+            digits.push(b'0' + r as u8);
+            v = q;
+            if v == U128::zero() {
+                break;
+            }
+        }
+        digits.reverse();
+        f.pad_integral(true, "", ::std::str::from_utf8(&digits[]).unwrap())
+    }
+}
+
+impl I128 {
+    pub fn from_i64(v: i64) -> I128 {
+        I128 { hi: if v < 0 { -1 } else { 0 }, lo: v as u64 }
+    }
+
+    /// Creates a value from its sign and magnitude or returns `None` if it doesn't fit.
+    pub fn from_magnitude(negative: bool, m: U128) -> Option<I128> {
+        let limit = 1 << 63;
+        match negative {
+            false if m.hi < limit => Some(I128 { hi: m.hi as i64, lo: m.lo }),
+            // The magnitude of the minimum is one larger than that of the maximum.
+            true if m.hi < limit || (m.hi == limit && m.lo == 0) => {
+                let v = m.negate();
+                Some(I128 { hi: v.hi as i64, lo: v.lo })
+            },
+            _ => None,
+        }
+    }
+
+    /// Returns the value if it fits into an `i64`.
+    pub fn to_i64(self) -> Option<i64> {
+        let negative = self.lo > i64::MAX as u64;
+        match self.hi {
+            0 if !negative => Some(self.lo as i64),
+            -1 if negative => Some(self.lo as i64),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for I128 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let v = U128 { hi: self.hi as u64, lo: self.lo };
+        let negative = self.hi < 0;
+        let m = if negative { v.negate() } else { v };
+        let s = format!("{}", m);
+        f.pad_integral(!negative, "", &s[])
+    }
+}
+
+/// Multiplies `a` and `b` and returns the upper and lower 64 bits of the product.
+fn mul_wide(a: u64, b: u64) -> (u64, u64) {
+    let mask = 0xFFFF_FFFF;
+    let (a1, a0) = (a >> 32, a & mask);
+    let (b1, b0) = (b >> 32, b & mask);
+    let p00 = a0 * b0;
+    let p01 = a0 * b1;
+    let p10 = a1 * b0;
+    let p11 = a1 * b1;
+    let mid = (p00 >> 32) + (p01 & mask) + (p10 & mask);
+    let lo = (p00 & mask) | mid << 32;
+    let hi = p11 + (p01 >> 32) + (p10 >> 32) + (mid >> 32);
+    (hi, lo)
+}
@@ -1,26 +1,66 @@
 #![crate_name = "scan"]
 #![crate_type = "lib"]
+#![feature(io, unsafe_destructor)]
 #![allow(unstable)]
 
 extern crate libc;
-
-use stdin::{Stdin};
+#[cfg(feature = "big")] extern crate num;
+
+pub use error::{ScanError, Expected, ReadError, NumError, ParseError};
+pub use from_scan::{FromScan};
+pub use int128::{U128, I128};
+pub use position::{Position};
+pub use source::{Source, Reader, Bytes};
+pub use stdin::{Stdin};
+#[cfg(feature = "big")] pub use num::bigint::{BigInt};
+
+use float::{Number, Parts};
+use position::{Tracker};
 use utf8::{UTF8};
-use std::num::{Int, Float};
-
+use std::any::{Any};
+use std::ascii::{AsciiExt};
+use std::collections::{RingBuf};
+use std::fmt;
+use std::i64;
+use std::io::{Read};
+use std::num::{Int};
+use std::str::{FromStr};
+
+mod buffer;
+mod error;
+mod float;
+mod from_scan;
+mod int128;
+mod position;
+mod source;
 mod stdin;
 mod utf8;
 
-pub fn stdin(drop_line: bool) -> Scanner {
-    Scanner {
-        drop_line: drop_line,
-        stdin: Stdin::new(),
-    }
+#[cfg(test)]
+mod test;
+
+pub fn stdin(drop_line: bool) -> Scanner<Stdin> {
+    Scanner::new(Stdin::new(), drop_line)
 }
 
-pub struct Scanner {
+/// Creates a scanner that reads from stdin and treats newlines like other whitespace.
+pub fn tokens() -> Scanner<Stdin> {
+    let mut scanner = Scanner::new(Stdin::new(), false);
+    scanner.set_multiline(true);
+    scanner
+}
+
+pub struct Scanner<S> {
     drop_line: bool,
-    stdin: Stdin,
+    multiline: bool,
+    separators: bool,
+    width: Option<usize>,
+    radix: Option<u32>,
+    done: bool,
+    error: Option<ReadError>,
+    tracker: Tracker,
+    lookahead: RingBuf<u8>,
+    source: S,
 }
 
 macro_rules! get {
@@ -29,124 +69,412 @@ macro_rules! get {
 
 macro_rules! get_or {
     ($s:ident, $b:block) => {
-        match $s.stdin.next() {
+        match $s.next() {
             Ok(b) => b,
             _ => $b,
         }
     }
 }
 
-macro_rules! digits {
-    ($s:ident, [$($range:pat, $lo:block)|+], $base:expr, $invert:expr, $ty:ty) => {{
-        let mut ok = false;
-        let mut res = 0us as $ty;
-        let base = $base as usize as $ty;
-        let mut iterations = 0us;
-        loop {
-            let next = get_or!($s, {break});
-            match next {
-                $(
-                    $range => {
-                        ok = true;
-                        res = res * base + (next - $lo) as $ty;
-                    },
-                )*
-                _ => {
-                    $s.stdin.push(next);
-                    break;
-                }
-            }
-            if $invert {
-                iterations += 1;
-            }
-        }
-        if $invert {
-            res /= base.upow(iterations);
-        }
-        match ok {
-            true => Some(res),
-            false => None,
-        }
-    }}
-}
-
 #[derive(Copy)]
 enum IntType {
     Binary,
     Octal,
     Decimal,
     Hex,
+    /// Any other radix between 2 and 36.
+    Radix(u32),
 }
 
-impl Scanner {
-    /// Parses binary digits
-    pub fn binary(&mut self) -> Option<u64> {
-        digits!(self, [b'0'...b'1', {b'0'}], 2, false, u64)
+impl IntType {
+    fn from_radix(radix: u32) -> IntType {
+        match radix {
+            2  => IntType::Binary,
+            8  => IntType::Octal,
+            10 => IntType::Decimal,
+            16 => IntType::Hex,
+            _  => IntType::Radix(radix),
+        }
     }
 
-    /// Parses binary digits after the .
-    pub fn binary_inv(&mut self) -> Option<f64> {
-        digits!(self, [b'0'...b'1', {b'0'}], 2, true, f64)
+    /// Returns the value of the digit `b` or `None` if it's not a digit of this type.
+    fn digit(self, b: u8) -> Option<u8> {
+        let d = match b {
+            b'0'...b'9' => b - b'0',
+            b'a'...b'z' => b - b'a' + 10,
+            b'A'...b'Z' => b - b'A' + 10,
+            _ => return None,
+        };
+        if (d as u32) < self.base() { Some(d) } else { None }
     }
 
-    /// Parses octal digits
-    pub fn octal(&mut self) -> Option<u64> {
-        digits!(self, [b'0'...b'7', {b'0'}], 8, false, u64)
+    fn base(self) -> u32 {
+        match self {
+            IntType::Binary  => 2,
+            IntType::Octal   => 8,
+            IntType::Decimal => 10,
+            IntType::Hex     => 16,
+            IntType::Radix(r) => r,
+        }
     }
 
-    /// Parses octal digits after the .
-    pub fn octal_inv(&mut self) -> Option<f64> {
-        digits!(self, [b'0'...b'7', {b'0'}], 8, true, f64)
+    /// Returns the letters that can follow a `0` to form the prefix of this type.
+    fn prefix(self) -> &'static [u8] {
+        match self {
+            IntType::Binary => b"bB",
+            IntType::Octal  => b"oO",
+            IntType::Hex    => b"xX",
+            _ => b"",
+        }
     }
+}
 
-    /// Parses decimal digits
-    pub fn decimal(&mut self) -> Option<u64> {
-        digits!(self, [b'0'...b'9', {b'0'}], 10, false, u64)
+impl<R: Read> Scanner<Reader<R>> {
+    /// Creates a scanner that reads from `r`.
+    pub fn from_reader(r: R) -> Scanner<Reader<R>> {
+        Scanner::new(Reader::new(r), false)
+    }
+}
+
+impl<'a> Scanner<Bytes<'a>> {
+    /// Creates a scanner that reads from `bytes`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Scanner<Bytes<'a>> {
+        Scanner::new(Bytes::new(bytes), false)
     }
 
-    /// Parses decimal digits after the .
-    pub fn decimal_inv(&mut self) -> Option<f64> {
-        digits!(self, [b'0'...b'9', {b'0'}], 10, true, f64)
+    /// Creates a scanner that reads from `s`.
+    pub fn from_str(s: &'a str) -> Scanner<Bytes<'a>> {
+        Scanner::from_bytes(s.as_bytes())
     }
+}
 
-    /// Parses hexadecimal digits
-    pub fn hexadecimal(&mut self) -> Option<u64> {
-        digits!(self, [b'0'...b'9', {b'0'} |
-                       b'a'...b'f', {b'a' - 10} |
-                       b'A'...b'F', {b'A' - 10}],
-                16, false, u64)
+impl<S: Source> Scanner<S> {
+    /// Creates a scanner that reads from `source`.
+    ///
+    /// If `drop_line` is `true`, the rest of the current line will be consumed when the
+    /// scanner is dropped.
+    pub fn new(source: S, drop_line: bool) -> Scanner<S> {
+        Scanner {
+            drop_line: drop_line,
+            multiline: false,
+            separators: false,
+            width: None,
+            radix: None,
+            done: false,
+            error: None,
+            tracker: Tracker::new(),
+            lookahead: RingBuf::new(),
+            source: source,
+        }
     }
 
-    /// Parses hexadecimal digits after the .
-    pub fn hexadecimal_inv(&mut self) -> Option<f64> {
-        digits!(self, [b'0'...b'9', {0} | b'a'...b'f', {10} | b'A'...b'F', {10}], 16, true, f64)
+    /// Enables or disables multi-line mode.
+    ///
+    /// By default a scanner stops at the end of the current line. In multi-line mode
+    /// newlines are ordinary whitespace and the scanner only stops at the end of the
+    /// input.
+    pub fn set_multiline(&mut self, multiline: bool) {
+        self.multiline = multiline;
     }
 
-    /// Parses digits depending on `ty`.
-    fn digits(&mut self, ty: IntType) -> Option<u64> {
-        match ty {
-            IntType::Binary  => self.binary(),
-            IntType::Octal   => self.octal(),
-            IntType::Decimal => self.decimal(),
-            IntType::Hex     => self.hexadecimal(),
+    /// Returns if multi-line mode is enabled.
+    pub fn multiline(&self) -> bool {
+        self.multiline
+    }
+
+    /// Enables or disables digit group separators.
+    ///
+    /// If enabled, integers and floats may contain single underscores between digits,
+    /// e.g., `1_000_000` or `0xFF_FF`. Underscores at the start or end of the digits and
+    /// double underscores make the number invalid.
+    pub fn set_separators(&mut self, separators: bool) {
+        self.separators = separators;
+    }
+
+    /// Limits the number of bytes that can be consumed.
+    ///
+    /// If `width` is `Some(n)`, the scanner behaves as if the current line ended after
+    /// the next `n` bytes until the limit is changed again. Bytes that are pushed back
+    /// count against the limit again. `None` removes the limit.
+    pub fn set_width(&mut self, width: Option<usize>) {
+        self.width = width;
+    }
+
+    /// Sets the radix of the integers that are parsed.
+    ///
+    /// If `radix` is `Some(r)`, integers are parsed in radix `r` and the prefixes `0b`,
+    /// `0o` and `0x` are optional for the radixes 2, 8 and 16 and not accepted
+    /// otherwise. Digits above 9 are the letters `a` to `z`, ignoring case. `None`
+    /// determines the radix from the prefix again.
+    ///
+    /// Panics if `r` is not between 2 and 36.
+    pub fn set_radix(&mut self, radix: Option<u32>) {
+        match radix {
+            Some(r) => assert!(r >= 2 && r <= 36, "radix must be between 2 and 36"),
+            None => { },
         }
+        self.radix = radix;
+    }
+
+    /// Continues scanning after the end of a line has been reached.
+    ///
+    /// A scanner stops at the end of the current line. This allows it to read the
+    /// next line. Errors of the source that have not been taken are discarded. The
+    /// macros call it when they are passed a scanner.
+    pub fn resume(&mut self) {
+        self.done = false;
+        self.error = None;
+    }
+
+    /// Takes the error that stopped the scanner.
+    ///
+    /// After the source has reached the end of the input or returned an error, the
+    /// scanner doesn't read from it again until the error has been taken or `resume`
+    /// has been called.
+    pub fn take_error(&mut self) -> Option<ReadError> {
+        self.error.take()
     }
 
-    /// Parses digits after the `.` depending on `ty`.
-    fn digits_inv(&mut self, ty: IntType) -> Option<f64> {
-        match ty {
-            IntType::Binary  => self.binary_inv(),
-            IntType::Octal   => self.octal_inv(),
-            IntType::Decimal => self.decimal_inv(),
-            IntType::Hex     => self.hexadecimal_inv(),
+    /// Consumes the rest of the current line including the newline.
+    pub fn skip_line(&mut self) {
+        loop {
+            match self.next() {
+                Ok(b'\n') | Err(_) => break,
+                Ok(_) => { },
+            }
         }
     }
 
+    /// Returns the number of bytes that have been consumed so far.
+    ///
+    /// The newline that terminates a line counts as consumed.
+    pub fn consumed(&self) -> usize {
+        self.tracker.position().offset
+    }
+
+    /// Returns the position of the next byte.
+    ///
+    /// Columns are counted in characters. Invalid UTF-8 sequences count as one character
+    /// per replacement character they decode to.
+    pub fn position(&self) -> Position {
+        self.tracker.position()
+    }
+
+    /// Creates an error for the specifier at `index` that expected `expected`.
+    ///
+    /// The error refers to the next byte.
+    pub fn mismatch(&mut self, index: usize, expected: Expected) -> ScanError {
+        let found = match self.peek() {
+            Some(b) => Ok(b),
+            None => Err(self.error.take().unwrap_or(ReadError::EndOfLine)),
+        };
+        ScanError {
+            index: index,
+            expected: expected,
+            found: found,
+            position: self.position(),
+            parse_error: None,
+        }
+    }
+
+    /// Returns the next byte of the current line without consuming it.
+    ///
+    /// In multi-line mode this is the next byte of the input.
+    pub fn peek(&mut self) -> Option<u8> {
+        self.peek_n(0)
+    }
+
+    /// Returns the byte `n` positions ahead in the current line without consuming it.
+    ///
+    /// `peek_n(0)` is the same as `peek()`. Returns `None` if the line or the input ends
+    /// before that position.
+    pub fn peek_n(&mut self, n: usize) -> Option<u8> {
+        if self.done {
+            return None;
+        }
+        match self.width {
+            Some(w) if n >= w => return None,
+            _ => { },
+        }
+        for i in range(0, n + 1) {
+            if i == self.lookahead.len() {
+                match self.read() {
+                    Ok(b) => self.lookahead.push_back(b),
+                    _ => return None,
+                }
+            }
+            if self.lookahead[i] == b'\n' && !self.multiline {
+                return None;
+            }
+        }
+        Some(self.lookahead[n])
+    }
+
+    /// Pushes `bytes` back so that they will be read again before the rest of the input.
+    ///
+    /// If the end of the current line has been reached, the newline that ended it is
+    /// pushed back as well. If `bytes` contains a newline, the end of the current line
+    /// is no longer considered reached and the line ends at that newline instead.
+    pub fn unread(&mut self, bytes: &[u8]) {
+        if bytes.len() == 0 {
+            return;
+        }
+        if self.done {
+            // The newline was consumed after `bytes`.
+            self.done = false;
+            self.lookahead.push_front(b'\n');
+            self.tracker.retreat(b'\n');
+            match self.width {
+                Some(ref mut w) => *w += 1,
+                None => { },
+            }
+        }
+        for &b in bytes.iter().rev() {
+            self.lookahead.push_front(b);
+            self.tracker.retreat(b);
+        }
+        if bytes.contains(&b'\n') {
+            self.done = false;
+        }
+        match self.width {
+            Some(ref mut w) => *w += bytes.len(),
+            None => { },
+        }
+    }
+
+    /// Returns the next byte of the current line.
+    fn next(&mut self) -> Result<u8, ()> {
+        if self.done || self.width == Some(0) {
+            return Err(());
+        }
+        let b = match self.lookahead.pop_front() {
+            Some(b) => b,
+            None => try!(self.read()),
+        };
+        self.tracker.advance(b);
+        match self.width {
+            Some(ref mut w) => *w -= 1,
+            None => { },
+        }
+        if b == b'\n' && !self.multiline {
+            self.done = true;
+            Err(())
+        } else {
+            Ok(b)
+        }
+    }
+
+    /// Pushes `b` back so that it will be returned by the next call to `next`.
+    fn push(&mut self, b: u8) {
+        self.unread(&[b]);
+    }
+
+    /// Reads a byte from the source and stores the error if this fails.
+    fn read(&mut self) -> Result<u8, ()> {
+        if self.error.is_some() {
+            return Err(());
+        }
+        match self.source.next() {
+            Ok(b) => Ok(b),
+            Err(e) => {
+                self.error = Some(e);
+                Err(())
+            },
+        }
+    }
+
+    /// Parses binary digits
+    ///
+    /// All digits are consumed even if the value doesn't fit into a `u64`.
+    pub fn binary(&mut self) -> Result<u64, NumError> {
+        narrow(self.digits(IntType::Binary, false))
+    }
+
+    /// Parses octal digits
+    ///
+    /// All digits are consumed even if the value doesn't fit into a `u64`.
+    pub fn octal(&mut self) -> Result<u64, NumError> {
+        narrow(self.digits(IntType::Octal, false))
+    }
+
+    /// Parses decimal digits
+    ///
+    /// All digits are consumed even if the value doesn't fit into a `u64`.
+    pub fn decimal(&mut self) -> Result<u64, NumError> {
+        narrow(self.digits(IntType::Decimal, false))
+    }
+
+    /// Parses hexadecimal digits
+    ///
+    /// All digits are consumed even if the value doesn't fit into a `u64`.
+    pub fn hexadecimal(&mut self) -> Result<u64, NumError> {
+        narrow(self.digits(IntType::Hex, false))
+    }
+
+    /// Parses digits depending on `ty`.
+    ///
+    /// `after` says if a digit has already been consumed. In this case the digits can
+    /// be empty and start with a separator.
+    fn digits(&mut self, ty: IntType, after: bool) -> Result<U128, NumError> {
+        let mut digits = vec!();
+        if !self.digit_values(ty, &mut digits, after) {
+            return Err(NumError::Invalid);
+        }
+        if digits.len() == 0 && !after {
+            return Err(NumError::Invalid);
+        }
+        let base = ty.base() as u64;
+        digits.iter().fold(Some(U128::zero()), |v, &d| {
+            v.and_then(|v| v.checked_mul(base))
+             .and_then(|v| v.checked_add(d as u64))
+        }).ok_or(NumError::Overflow)
+    }
+
     /// Parses an unsigned integer including its prefix
-    pub fn unsigned_integer(&mut self) -> Option<u64> {
-        match self.classify() {
-            (ty, true)  => self.digits(ty).or(Some(0)),
-            (ty, false) => self.digits(ty),
+    pub fn unsigned_integer(&mut self) -> Result<u64, NumError> {
+        narrow(self.unsigned_integer128())
+    }
+
+    /// Parses an unsigned integer including its prefix into a `U128`.
+    pub fn unsigned_integer128(&mut self) -> Result<U128, NumError> {
+        let (ty, cons) = self.integer_type();
+        self.digits(ty, cons)
+    }
+
+    /// Parses an unsigned integer in radix `radix`.
+    ///
+    /// See `set_radix` for the accepted prefixes and digits. Panics if `radix` is not
+    /// between 2 and 36.
+    pub fn integer_radix(&mut self, radix: u32) -> Result<u64, NumError> {
+        let old = self.radix;
+        self.set_radix(Some(radix));
+        let res = self.unsigned_integer();
+        self.radix = old;
+        res
+    }
+
+    /// Returns the type of the following integer depending on the radix that has been
+    /// set.
+    ///
+    /// See `classify` for the meaning of the second value.
+    fn integer_type(&mut self) -> (IntType, bool) {
+        match self.radix {
+            Some(r) => {
+                let ty = IntType::from_radix(r);
+                // The prefix is only consumed if a digit follows it.
+                let prefix = match (self.peek(), self.peek_n(1), self.peek_n(2)) {
+                    (Some(b'0'), Some(p), Some(d)) => {
+                        ty.prefix().contains(&p) && ty.digit(d).is_some()
+                    },
+                    _ => false,
+                };
+                if prefix {
+                    let _ = self.next();
+                    let _ = self.next();
+                }
+                (ty, false)
+            },
+            None => self.classify(),
         }
     }
 
@@ -155,61 +483,228 @@ impl Scanner {
     fn classify(&mut self) -> (IntType, bool) {
         match get_or!(self, {return (IntType::Decimal, false)}) {
             b'0' => {
-                match get_or!(self, {return (IntType::Decimal, true)}) {
-                    b'x' | b'X' => (IntType::Hex, false),
-                    b'o' | b'O' => (IntType::Octal, false),
-                    b'b' | b'B' => (IntType::Binary, false),
-                    b => {
-                        self.stdin.push(b);
-                        (IntType::Decimal, true)
+                let ty = match self.peek() {
+                    Some(b'x') | Some(b'X') => IntType::Hex,
+                    Some(b'o') | Some(b'O') => IntType::Octal,
+                    Some(b'b') | Some(b'B') => IntType::Binary,
+                    _ => return (IntType::Decimal, true),
+                };
+                // The prefix is only consumed if a digit follows it.
+                match self.peek_n(1).and_then(|d| ty.digit(d)) {
+                    Some(_) => {
+                        let _ = self.next();
+                        (ty, false)
                     },
+                    None => (IntType::Decimal, true),
                 }
             },
             b => {
-                self.stdin.push(b);
+                self.push(b);
                 (IntType::Decimal, false)
             },
         }
     }
 
-    /// Parses the sign of the following integer.
-    fn sign(&mut self) -> i64 {
-        match get_or!(self, {return 1}) {
-            b'+' => 1,
-            b'-' => -1,
+    /// Parses the sign of the following number and returns if it is negative.
+    fn sign(&mut self) -> bool {
+        match get_or!(self, {return false}) {
+            b'+' => false,
+            b'-' => true,
             b => {
-                self.stdin.push(b);
-                1
+                self.push(b);
+                false
             },
         }
     }
 
     /// Parses a signed integer.
-    pub fn signed_integer(&mut self) -> Option<i64> {
-        let sign = self.sign();
-        self.unsigned_integer().map(|v| sign * v as i64)
+    ///
+    /// Accepts every value from `i64::MIN` to `i64::MAX`.
+    pub fn signed_integer(&mut self) -> Result<i64, NumError> {
+        let v = try!(self.signed_integer128());
+        v.to_i64().ok_or(NumError::Overflow)
+    }
+
+    /// Parses a signed integer into an `I128`.
+    ///
+    /// Accepts every value from `-2^127` to `2^127 - 1`.
+    pub fn signed_integer128(&mut self) -> Result<I128, NumError> {
+        let negative = self.sign();
+        let v = try!(self.unsigned_integer128());
+        I128::from_magnitude(negative, v).ok_or(NumError::Overflow)
+    }
+
+    /// Parses a signed integer of arbitrary size.
+    ///
+    /// Only available with the `big` feature.
+    #[cfg(feature = "big")]
+    pub fn big_integer(&mut self) -> Result<BigInt, NumError> {
+        use num::bigint::{BigUint, Sign};
+        use std::char;
+        use std::num::{FromStrRadix};
+
+        let negative = self.sign();
+        let (ty, cons) = self.integer_type();
+        let mut digits = vec!();
+        if cons {
+            digits.push(0);
+        }
+        if !self.digit_values(ty, &mut digits, cons) || digits.len() == 0 {
+            return Err(NumError::Invalid);
+        }
+        let s: String = digits.iter().map(|&d| {
+            char::from_digit(d as u32, ty.base()).unwrap()
+        }).collect();
+        let v: BigUint = FromStrRadix::from_str_radix(&s[], ty.base()).unwrap();
+        Ok(BigInt::from_biguint(if negative { Sign::Minus } else { Sign::Plus }, v))
     }
 
     /// Parses a floating point number.
+    ///
+    /// Decimal numbers can have an exponent introduced by `e` or `E`, hexadecimal
+    /// numbers a binary exponent introduced by `p` or `P`. `inf`, `infinity` and `nan`
+    /// are accepted regardless of case.
+    ///
+    /// Decimal numbers are rounded like `str::parse`. Binary, octal and hexadecimal
+    /// numbers are rounded to the nearest value, ties to even.
     pub fn float(&mut self) -> Option<f64> {
-        let sign = self.sign() as f64;
+        self.float_parts().and_then(|p| p.to_f64())
+    }
+
+    /// Parses a floating point number with single precision.
+    ///
+    /// The result is rounded directly to the precision of an `f32`.
+    pub fn float32(&mut self) -> Option<f32> {
+        self.float_parts().and_then(|p| p.to_f32())
+    }
+
+    /// Parses a floating point number without rounding it.
+    fn float_parts(&mut self) -> Option<Number> {
+        let negative = self.sign();
+        if self.keyword("nan") {
+            return Some(Number::NaN);
+        }
+        if self.keyword("inf") {
+            self.keyword("inity");
+            return Some(Number::Infinite(negative));
+        }
         let (ty, cons) = self.classify();
-        let pre = match (self.digits(ty), cons) {
-            (Some(d), _) => d as f64,
-            (None, true) => 0.0,
-            _ => return None,
-        };
-        match get_or!(self, {return Some(pre)}) {
-            b'.' => { },
-            b => {
-                self.stdin.push(b);
-                return Some(pre);
+        let mut digits = vec!();
+        if cons {
+            digits.push(0);
+        }
+        if !self.digit_values(ty, &mut digits, cons) {
+            return None;
+        }
+        let int_digits = digits.len();
+        if int_digits == 0 {
+            return None;
+        }
+        let valid = match self.next() {
+            Ok(b'.') => self.digit_values(ty, &mut digits, false),
+            Ok(b) => {
+                self.push(b);
+                true
             },
+            Err(()) => true,
+        };
+        if !valid {
+            return None;
         }
-        match self.digits_inv(ty) {
-            Some(d) => Some(sign * (pre + d)),
-            _ => Some(sign * pre),
+        let exp = match ty {
+            IntType::Decimal => self.exponent(b"eE"),
+            IntType::Hex     => self.exponent(b"pP"),
+            _ => 0,
+        };
+        Some(Number::Finite(Parts {
+            negative: negative,
+            radix: ty.base(),
+            digits: digits,
+            int_digits: int_digits,
+            exp: exp,
+        }))
+    }
+
+    /// Parses an exponent introduced by one of the bytes in `marks`.
+    ///
+    /// Returns `0` without consuming anything if there is no complete exponent.
+    fn exponent(&mut self, marks: &[u8]) -> i64 {
+        match self.peek() {
+            Some(b) if marks.contains(&b) => { },
+            _ => return 0,
+        }
+        let digit = match self.peek_n(1) {
+            Some(b'+') | Some(b'-') => 2,
+            _ => 1,
+        };
+        match self.peek_n(digit) {
+            Some(b'0'...b'9') => { },
+            _ => return 0,
+        }
+        let _ = self.next();
+        let negative = self.sign();
+        let mut exp = 0i64;
+        loop {
+            let b = get_or!(self, {break});
+            match b {
+                // Larger exponents make every number infinite or zero.
+                b'0'...b'9' => if exp < 1_000_000_000 {
+                    exp = exp * 10 + (b - b'0') as i64;
+                },
+                _ => {
+                    self.push(b);
+                    break;
+                },
+            }
         }
+        if negative { -exp } else { exp }
+    }
+
+    /// Consumes `word` if the input continues with it, ignoring ASCII case.
+    ///
+    /// `word` has to be lowercase.
+    fn keyword(&mut self, word: &str) -> bool {
+        for (i, &b) in word.as_bytes().iter().enumerate() {
+            match self.peek_n(i) {
+                Some(c) if c.to_ascii_lowercase() == b => { },
+                _ => return false,
+            }
+        }
+        for _ in range(0, word.len()) {
+            let _ = self.next();
+        }
+        true
+    }
+
+    /// Appends the values of the following digits to `digits`.
+    ///
+    /// `after` says if a digit has already been consumed. Returns `false` if the digits
+    /// contain a misplaced separator.
+    fn digit_values(&mut self, ty: IntType, digits: &mut Vec<u8>, after: bool) -> bool {
+        let mut prev = after;
+        loop {
+            let b = get_or!(self, {break});
+            match ty.digit(b) {
+                Some(d) => {
+                    digits.push(d);
+                    prev = true;
+                },
+                None if b == b'_' && self.separators && prev => {
+                    match self.peek().and_then(|c| ty.digit(c)) {
+                        Some(_) => prev = false,
+                        None => {
+                            self.push(b);
+                            return false;
+                        },
+                    }
+                },
+                None => {
+                    self.push(b);
+                    break;
+                },
+            }
+        }
+        true
     }
 
     /// Reads a word from the stream.
@@ -220,16 +715,31 @@ impl Scanner {
         self.string(true, false)
     }
 
+    /// Reads a word that ends before the first whitespace character or `stop`.
+    pub fn word_before(&mut self, stop: u8) -> String {
+        self.take_while(|b| b != stop && !is_whitespace(b))
+    }
+
     /// Reads a string from the stream.
     pub fn string(&mut self, word: bool, line: bool) -> String {
+        let res = self.take_while(|b| !(word && is_whitespace(b)) && !(line && b == b'\n'));
+        if line {
+            // Consume the newline.
+            let _ = self.next();
+        }
+        res
+    }
+
+    /// Reads the longest sequence of bytes for which `f` returns `true`.
+    ///
+    /// Invalid UTF-8 sequences will be replaced by U+FFFD.
+    pub fn take_while<F: FnMut(u8) -> bool>(&mut self, mut f: F) -> String {
         let mut res = String::new();
         let mut utf8 = UTF8::new();
         loop {
             let next = get_or!(self, {break});
-            if word && is_whitespace(next) {
-                self.stdin.push(next);
-                break;
-            } else if line && next == b'\n' {
+            if !f(next) {
+                self.push(next);
                 break;
             }
             match utf8.push(next) {
@@ -248,12 +758,95 @@ impl Scanner {
         res
     }
 
+    /// Parses a value of type `T`.
+    pub fn scan<T: FromScan>(&mut self) -> Result<T, Expected> {
+        FromScan::scan(self)
+    }
+
+    /// Reads a word and parses it with `FromStr`.
+    ///
+    /// If parsing fails, the word is pushed back so that it can be read again.
+    pub fn parse<T>(&mut self) -> Result<T, ParseError>
+        where T: FromStr, <T as FromStr>::Err: Any + Send + fmt::Debug,
+    {
+        let word = self.word();
+        self.parse_word(word)
+    }
+
+    /// Like `parse` but the word ends before `stop`.
+    pub fn parse_before<T>(&mut self, stop: u8) -> Result<T, ParseError>
+        where T: FromStr, <T as FromStr>::Err: Any + Send + fmt::Debug,
+    {
+        let word = self.word_before(stop);
+        self.parse_word(word)
+    }
+
+    fn parse_word<T>(&mut self, word: String) -> Result<T, ParseError>
+        where T: FromStr, <T as FromStr>::Err: Any + Send + fmt::Debug,
+    {
+        match word.parse() {
+            Ok(v) => Ok(v),
+            Err(e) => {
+                self.unread(word.as_bytes());
+                Err(ParseError::new(e))
+            },
+        }
+    }
+
+    /// Reads one character.
+    ///
+    /// Whitespace is read like every other character. An invalid UTF-8 sequence is read
+    /// as U+FFFD.
+    pub fn character(&mut self) -> Option<char> {
+        let mut utf8 = UTF8::new();
+        loop {
+            let b = match self.next() {
+                Ok(b) => b,
+                Err(()) if utf8.pending() => return Some(utf8::REPLACEMENT),
+                Err(()) => return None,
+            };
+            // A sequence that is interrupted by the start of another one is invalid.
+            if utf8.pending() && (b < 0x80 || b >= 0xC0) {
+                self.push(b);
+                return Some(utf8::REPLACEMENT);
+            }
+            match utf8.push(b) {
+                (Some(c), _) | (None, Some(c)) => return Some(c),
+                _ => { },
+            }
+        }
+    }
+
+    /// Parses a boolean.
+    ///
+    /// `true`, `yes` and `1` are read as `true`, `false`, `no` and `0` as `false`,
+    /// regardless of case. The word must not be followed by a letter, digit or `_`.
+    pub fn boolean(&mut self) -> Option<bool> {
+        static WORDS: &'static [(&'static str, bool)] = &[
+            ("true", true), ("false", false),
+            ("yes",  true), ("no",    false),
+            ("1",    true), ("0",     false),
+        ];
+        for &(word, value) in WORDS.iter() {
+            match self.peek_n(word.len()) {
+                Some(b'a'...b'z') | Some(b'A'...b'Z') | Some(b'0'...b'9') | Some(b'_') => {
+                    continue
+                },
+                _ => { },
+            }
+            if self.keyword(word) {
+                return Some(value);
+            }
+        }
+        None
+    }
+
     /// Read until the first non-whitespace character.
     pub fn whitespace(&mut self) {
         loop {
             let b = get_or!(self, {return});
             if !is_whitespace(b) {
-                self.stdin.push(b);
+                self.push(b);
                 return;
             }
         }
@@ -266,33 +859,71 @@ impl Scanner {
     /// `None` otherwise.
     pub fn literal(&mut self, lit: &str) -> Option<()> {
         for &b in lit.as_bytes().iter() {
-            match self.stdin.next() {
+            match self.next() {
                 Ok(c) if c == b => { },
                 Ok(c) => {
-                    self.stdin.push(c);
+                    self.push(c);
                     return None;
                 },
-                _ => break,
+                _ => return None,
             }
         }
         Some(())
     }
 
+    /// Reads the separator `sep` and the whitespace around it.
+    ///
+    /// Returns `None` and consumes nothing if the input doesn't continue with the
+    /// separator.
+    pub fn separator(&mut self, sep: &str) -> Option<()> {
+        let mut n = 0;
+        loop {
+            match self.peek_n(n) {
+                Some(b) if is_whitespace(b) => n += 1,
+                _ => break,
+            }
+        }
+        for (i, &b) in sep.as_bytes().iter().enumerate() {
+            if self.peek_n(n + i) != Some(b) {
+                return None;
+            }
+        }
+        self.whitespace();
+        let res = self.literal(sep);
+        self.whitespace();
+        res
+    }
+
     /// Reads until the first newline.
-    pub fn line(&mut self) -> String {
-        self.string(false, true)
+    ///
+    /// Returns `None` if the input ends or the source returns an error before the line
+    /// starts.
+    pub fn line(&mut self) -> Option<String> {
+        match self.next() {
+            Ok(b) => self.push(b),
+            Err(()) if self.done => return Some(String::new()),
+            Err(()) => return None,
+        }
+        Some(self.string(false, true))
     }
 }
 
-impl Drop for Scanner {
+#[unsafe_destructor]
+impl<S: Source> Drop for Scanner<S> {
     fn drop(&mut self) {
         if self.drop_line {
-            loop {
-                if self.stdin.next().is_err() {
-                    break;
-                }
-            }
+            self.skip_line();
         }
+        let rest: Vec<u8> = self.lookahead.iter().map(|&b| b).collect();
+        self.source.restore(&rest[]);
+    }
+}
+
+/// Converts the result of `digits` to a `u64`.
+fn narrow(v: Result<U128, NumError>) -> Result<u64, NumError> {
+    match v {
+        Ok(v) => v.to_u64().ok_or(NumError::Overflow),
+        Err(e) => Err(e),
     }
 }
 
@@ -304,25 +935,3 @@ fn is_whitespace(b: u8) -> bool {
         _ => false,
     }
 }
-
-trait UniversalPow {
-    fn upow(self, n: usize) -> Self;
-}
-
-impl<T: Int> UniversalPow for T {
-    fn upow(self, n: usize) -> T {
-        self.pow(n)
-    }
-}
-
-impl UniversalPow for f32 {
-    fn upow(self, n: usize) -> f32 {
-        self.powi(n as i32)
-    }
-}
-
-impl UniversalPow for f64 {
-    fn upow(self, n: usize) -> f64 {
-        self.powi(n as i32)
-    }
-}
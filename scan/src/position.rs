@@ -0,0 +1,84 @@
+use std::fmt;
+
+use utf8::{UTF8};
+
+/// A position in the input of a scanner.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Position {
+    /// The number of bytes before this position.
+    pub offset: usize,
+    /// The line, starting at 1.
+    pub line: usize,
+    /// The column in characters, starting at 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// Keeps track of the position while bytes are consumed and unread.
+pub struct Tracker {
+    pos: Position,
+    utf8: UTF8,
+    /// The column at the end of the previous line.
+    prev_column: usize,
+}
+
+impl Tracker {
+    pub fn new() -> Tracker {
+        Tracker {
+            pos: Position { offset: 0, line: 1, column: 1 },
+            utf8: UTF8::new(),
+            prev_column: 1,
+        }
+    }
+
+    pub fn position(&self) -> Position {
+        self.pos
+    }
+
+    /// Moves the position past `b`.
+    ///
+    /// The column only advances once a character has been decoded completely.
+    pub fn advance(&mut self, b: u8) {
+        self.pos.offset += 1;
+        if b == b'\n' {
+            self.utf8 = UTF8::new();
+            self.prev_column = self.pos.column;
+            self.pos.line += 1;
+            self.pos.column = 1;
+            return;
+        }
+        match self.utf8.push(b) {
+            (Some(_), Some(_)) => self.pos.column += 2,
+            (Some(_), None) | (None, Some(_)) => self.pos.column += 1,
+            _ => { },
+        }
+    }
+
+    /// Moves the position back before `b`.
+    ///
+    /// This is exact if `b` is the byte that was consumed last. Only the column at the
+    /// end of the previous line is remembered. Continuation bytes don't count as
+    /// columns, so the column moves back once the lead byte of a character is unread.
+    pub fn retreat(&mut self, b: u8) {
+        if self.pos.offset == 0 {
+            return;
+        }
+        self.pos.offset -= 1;
+        if b == b'\n' {
+            if self.pos.line > 1 {
+                self.pos.line -= 1;
+                self.pos.column = self.prev_column;
+            }
+        } else if b & 0xC0 == 0x80 {
+            // Continuation byte.
+        } else if !self.utf8.pending() && self.pos.column > 1 {
+            self.pos.column -= 1;
+        }
+        self.utf8 = UTF8::new();
+    }
+}
@@ -0,0 +1,97 @@
+use std::io::{Read};
+
+use buffer::{Buffer, DEFAULT_CAPACITY};
+use error::{ReadError};
+
+/// A source of bytes for a `Scanner`.
+pub trait Source {
+    /// Returns the next byte.
+    ///
+    /// Returns `ReadError::EndOfInput` if no more bytes are available. Sources never
+    /// return `ReadError::EndOfLine`.
+    fn next(&mut self) -> Result<u8, ReadError>;
+
+    /// Takes back bytes that have been read from the source but were not consumed.
+    ///
+    /// This is called when a `Scanner` is dropped so that sources which outlive the
+    /// scanner, e.g., stdin, don't lose them. The default implementation discards the
+    /// bytes.
+    fn restore(&mut self, _bytes: &[u8]) { }
+}
+
+/// A `Source` that reads from an object implementing `Read`.
+///
+/// This covers files, sockets, pipes and everything that implements `BufRead`. The
+/// input is read in chunks.
+pub struct Reader<R> {
+    inner: R,
+    capacity: usize,
+    buf: Buffer,
+}
+
+impl<R: Read> Reader<R> {
+    pub fn new(inner: R) -> Reader<R> {
+        Reader::with_capacity(DEFAULT_CAPACITY, inner)
+    }
+
+    /// Creates a `Reader` that reads at most `capacity` bytes at once.
+    ///
+    /// A capacity of `1` never takes more bytes from `inner` than are consumed.
+    pub fn with_capacity(capacity: usize, inner: R) -> Reader<R> {
+        Reader {
+            inner: inner,
+            capacity: if capacity > 0 { capacity } else { 1 },
+            buf: Buffer::new(),
+        }
+    }
+
+    /// Returns the underlying reader.
+    ///
+    /// Bytes that have been read from it but not consumed are lost.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Source for Reader<R> {
+    fn next(&mut self) -> Result<u8, ReadError> {
+        match self.buf.next() {
+            Some(b) => return Ok(b),
+            None => { },
+        }
+        let inner = &mut self.inner;
+        try!(self.buf.fill(self.capacity, |data| inner.read(data)));
+        self.buf.next().ok_or(ReadError::EndOfInput)
+    }
+
+    fn restore(&mut self, bytes: &[u8]) {
+        self.buf.restore(bytes);
+    }
+}
+
+/// A `Source` that reads from a byte slice.
+pub struct Bytes<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Bytes<'a> {
+    pub fn new(bytes: &'a [u8]) -> Bytes<'a> {
+        Bytes {
+            bytes: bytes,
+            pos: 0,
+        }
+    }
+}
+
+impl<'a> Source for Bytes<'a> {
+    fn next(&mut self) -> Result<u8, ReadError> {
+        match self.bytes.get(self.pos) {
+            Some(&b) => {
+                self.pos += 1;
+                Ok(b)
+            },
+            None => Err(ReadError::EndOfInput),
+        }
+    }
+}
@@ -1,43 +1,92 @@
 use libc;
 
-static mut pushed: Option<u8> = None;
+use std::io;
+use std::mem;
+use std::sync::{StaticMutex, MUTEX_INIT};
 
+use buffer::{Buffer, DEFAULT_CAPACITY};
+use error::{ReadError};
+use source::{Source};
+
+/// Protects `BUFFER`.
+static LOCK: StaticMutex = MUTEX_INIT;
+
+/// Bytes that have been read from file descriptor 0 but not yet consumed.
+static mut BUFFER: *mut Buffer = 0 as *mut Buffer;
+
+/// Runs `f` with exclusive access to the buffer shared by all `Stdin` objects.
+fn with_buffer<T, F: FnOnce(&mut Buffer) -> T>(f: F) -> T {
+    let _guard = LOCK.lock();
+    unsafe {
+        if BUFFER.is_null() {
+            BUFFER = mem::transmute(Box::new(Buffer::new()));
+        }
+        f(&mut *BUFFER)
+    }
+}
+
+/// A `Source` that reads from file descriptor 0.
+///
+/// Input is read in chunks into a buffer that is shared by all `Stdin` objects, so bytes
+/// that one scanner has read but not consumed are seen by the next scanner.
 pub struct Stdin {
-    done: bool,
+    capacity: usize,
+    careful: bool,
 }
 
 impl Stdin {
     pub fn new() -> Stdin {
-        Stdin { done: false }
+        Stdin::with_capacity(DEFAULT_CAPACITY)
     }
 
-    pub fn next(&mut self) -> Result<u8, ()> {
-        if self.done {
-            return Err(());
+    /// Creates a `Stdin` that reads at most `capacity` bytes at once.
+    pub fn with_capacity(capacity: usize) -> Stdin {
+        Stdin {
+            capacity: if capacity > 0 { capacity } else { 1 },
+            careful: false,
         }
+    }
 
-        unsafe {
-            if pushed.is_some() {
-                return Ok(pushed.take().unwrap());
-            }
-        }
+    /// Enables or disables careful mode.
+    ///
+    /// In careful mode no bytes after the newline that ends the current line are taken
+    /// from file descriptor 0. This is important if other code, e.g., a child process,
+    /// reads from the same file descriptor. Terminals deliver at most one line per read
+    /// so they are still read in chunks. Other files are read one byte at a time.
+    pub fn set_careful(&mut self, careful: bool) {
+        self.careful = careful;
+    }
 
-        let mut buf = [0u8];
-        match unsafe { libc::read(0, buf.as_mut_ptr() as *mut libc::c_void, 1) } {
-            -1 | 0 => Err(()),
-            _ if buf[0] == b'\n' => {
-                self.done = true;
-                Err(())
-            },
-            _ => Ok(buf[0]),
+    /// Returns the number of bytes the next read from file descriptor 0 may return.
+    fn chunk_size(&self) -> usize {
+        if self.careful && unsafe { libc::isatty(0) } != 1 {
+            1
+        } else {
+            self.capacity
         }
     }
+}
 
-    pub fn push(&mut self, b: u8) {
-        unsafe {
-            if pushed.is_none() {
-                pushed = Some(b);
+impl Source for Stdin {
+    fn next(&mut self) -> Result<u8, ReadError> {
+        let n = self.chunk_size();
+        with_buffer(|buf| {
+            match buf.next() {
+                Some(b) => return Ok(b),
+                None => { },
             }
-        }
+            try!(buf.fill(n, |data| {
+                match unsafe { libc::read(0, data.as_mut_ptr() as *mut libc::c_void,
+                                          data.len() as libc::size_t) } {
+                    -1 => Err(io::Error::last_os_error()),
+                    n => Ok(n as usize),
+                }
+            }));
+            buf.next().ok_or(ReadError::EndOfInput)
+        })
+    }
+
+    fn restore(&mut self, bytes: &[u8]) {
+        with_buffer(|buf| buf.restore(bytes));
     }
 }
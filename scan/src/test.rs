@@ -0,0 +1,250 @@
+use std::{isize, mem};
+use std::num::{Float};
+
+use error::{Expected, NumError};
+use position::{Position};
+use from_scan::{FromScan};
+use int128::{U128, I128};
+use source::{Bytes};
+use super::{Scanner};
+
+fn scan<T: FromScan>(s: &str) -> Result<T, Expected> {
+    Scanner::from_str(s).scan()
+}
+
+#[test]
+fn i8_range() {
+    assert_eq!(scan::<i8>("-128"), Ok(-128));
+    assert_eq!(scan::<i8>("127"), Ok(127));
+    assert_eq!(scan::<i8>("-129"), Err(Expected::Range("i8")));
+    assert_eq!(scan::<i8>("128"), Err(Expected::Range("i8")));
+}
+
+#[test]
+fn i16_range() {
+    assert_eq!(scan::<i16>("-32768"), Ok(-32768));
+    assert_eq!(scan::<i16>("32767"), Ok(32767));
+    assert_eq!(scan::<i16>("-32769"), Err(Expected::Range("i16")));
+    assert_eq!(scan::<i16>("32768"), Err(Expected::Range("i16")));
+}
+
+#[test]
+fn i32_range() {
+    assert_eq!(scan::<i32>("-2147483648"), Ok(-2147483648));
+    assert_eq!(scan::<i32>("2147483647"), Ok(2147483647));
+    assert_eq!(scan::<i32>("-2147483649"), Err(Expected::Range("i32")));
+    assert_eq!(scan::<i32>("2147483648"), Err(Expected::Range("i32")));
+}
+
+#[test]
+fn i64_range() {
+    assert_eq!(scan::<i64>("-9223372036854775808"), Ok(-9223372036854775807 - 1));
+    assert_eq!(scan::<i64>("9223372036854775807"), Ok(9223372036854775807));
+    assert_eq!(scan::<i64>("-9223372036854775809"), Err(Expected::Range("i64")));
+    assert_eq!(scan::<i64>("9223372036854775808"), Err(Expected::Range("i64")));
+}
+
+#[test]
+fn isize_range() {
+    let min = format!("{}", isize::MIN);
+    let max = format!("{}", isize::MAX);
+    assert_eq!(scan::<isize>(&min[]), Ok(isize::MIN));
+    assert_eq!(scan::<isize>(&max[]), Ok(isize::MAX));
+    assert_eq!(scan::<isize>("-9223372036854775809"), Err(Expected::Range("isize")));
+    assert_eq!(scan::<isize>("9223372036854775808"), Err(Expected::Range("isize")));
+}
+
+#[test]
+fn signed_integer_range() {
+    let mut s = Scanner::from_str("-9223372036854775808 9223372036854775807");
+    assert_eq!(s.signed_integer(), Ok(-9223372036854775807 - 1));
+    s.whitespace();
+    assert_eq!(s.signed_integer(), Ok(9223372036854775807));
+
+    let mut s = Scanner::from_str("-9223372036854775809");
+    assert_eq!(s.signed_integer(), Err(NumError::Overflow));
+    let mut s = Scanner::from_str("9223372036854775808");
+    assert_eq!(s.signed_integer(), Err(NumError::Overflow));
+}
+
+#[test]
+fn negative_zero() {
+    assert_eq!(Scanner::from_str("-0").signed_integer(), Ok(0));
+    assert_eq!(scan::<i8>("-0"), Ok(0));
+    assert_eq!(scan::<i64>("-0"), Ok(0));
+}
+
+#[test]
+fn multi_byte_characters() {
+    let mut s = Scanner::from_str("é€𝄞x");
+    assert_eq!(s.character(), Some('é'));
+    assert_eq!(s.character(), Some('€'));
+    assert_eq!(s.character(), Some('𝄞'));
+    assert_eq!(s.character(), Some('x'));
+    assert_eq!(s.character(), None);
+}
+
+#[test]
+fn unread_multi_byte_characters() {
+    let mut s = Scanner::from_str("aé€𝄞 b");
+    let word = s.word();
+    assert_eq!(s.position().column, 5);
+    s.unread(word.as_bytes());
+    assert_eq!(s.position().column, 1);
+    assert_eq!(s.position().offset, 0);
+    assert_eq!(s.word(), word);
+    assert_eq!(s.position().column, 5);
+}
+
+#[test]
+fn prefix_without_digits() {
+    let mut s = Scanner::from_str("0b 0x1F 0xg 0o8");
+    assert_eq!(s.unsigned_integer(), Ok(0));
+    assert_eq!(s.word(), "b");
+    s.whitespace();
+    assert_eq!(s.unsigned_integer(), Ok(31));
+    s.whitespace();
+    assert_eq!(s.unsigned_integer(), Ok(0));
+    assert_eq!(s.word(), "xg");
+    s.whitespace();
+    assert_eq!(s.unsigned_integer(), Ok(0));
+    assert_eq!(s.word(), "o8");
+}
+
+#[test]
+fn u128_range() {
+    let max = U128 { hi: 0xFFFF_FFFF_FFFF_FFFF, lo: 0xFFFF_FFFF_FFFF_FFFF };
+    assert_eq!(scan::<U128>("340282366920938463463374607431768211455"), Ok(max));
+    assert_eq!(scan::<U128>("340282366920938463463374607431768211456"),
+               Err(Expected::Range("u128")));
+    assert_eq!(scan::<U128>("0x10000000000000000"), Ok(U128 { hi: 1, lo: 0 }));
+    assert_eq!(format!("{}", max), "340282366920938463463374607431768211455");
+}
+
+#[test]
+fn i128_range() {
+    let min = I128 { hi: -9223372036854775807 - 1, lo: 0 };
+    let max = I128 { hi: 9223372036854775807, lo: 0xFFFF_FFFF_FFFF_FFFF };
+    assert_eq!(scan::<I128>("-170141183460469231731687303715884105728"), Ok(min));
+    assert_eq!(scan::<I128>("170141183460469231731687303715884105727"), Ok(max));
+    assert_eq!(scan::<I128>("-170141183460469231731687303715884105729"),
+               Err(Expected::Range("i128")));
+    assert_eq!(scan::<I128>("170141183460469231731687303715884105728"),
+               Err(Expected::Range("i128")));
+    assert_eq!(scan::<I128>("-1"), Ok(I128::from_i64(-1)));
+    assert_eq!(format!("{}", min), "-170141183460469231731687303715884105728");
+    assert_eq!(min.to_i64(), None);
+    assert_eq!(I128::from_i64(-5).to_i64(), Some(-5));
+}
+
+#[test]
+fn parse_failure_at_end_of_line() {
+    let mut s = Scanner::from_str("abc\n");
+    assert!(s.parse::<u8>().is_err());
+    assert_eq!(s.position(), Position { offset: 0, line: 1, column: 1 });
+    assert_eq!(s.peek(), Some(b'a'));
+    assert_eq!(s.word(), "abc");
+    assert_eq!(s.position(), Position { offset: 4, line: 2, column: 1 });
+}
+
+fn f64_bits(s: &str) -> u64 {
+    let v = Scanner::from_str(s).float().unwrap();
+    unsafe { mem::transmute(v) }
+}
+
+fn f32_bits(s: &str) -> u32 {
+    let v = Scanner::from_str(s).float32().unwrap();
+    unsafe { mem::transmute(v) }
+}
+
+#[test]
+fn float_ties_to_even() {
+    assert_eq!(f64_bits("0x1.00000000000008p0"), 0x3FF0000000000000);
+    assert_eq!(f64_bits("0x1.00000000000018p0"), 0x3FF0000000000002);
+    // Octal digits don't line up with the bits of the mantissa.
+    assert_eq!(f64_bits("0o1.000000000000000002"), 0x3FF0000000000000);
+    assert_eq!(f64_bits("0o1.000000000000000006"), 0x3FF0000000000002);
+}
+
+#[test]
+fn float_sticky_bits() {
+    // The bits that decide the rounding are beyond the 64 bits that are kept.
+    assert_eq!(f64_bits("0x1.0000000000000800000001p0"), 0x3FF0000000000001);
+    assert_eq!(f64_bits("0x1.0000000000000800000000p0"), 0x3FF0000000000000);
+}
+
+#[test]
+fn float_subnormals() {
+    assert_eq!(f64_bits("0x1p-1074"), 1);
+    assert_eq!(f64_bits("0x1p-1075"), 0);
+    assert_eq!(f64_bits("0x1.0000001p-1075"), 1);
+    assert_eq!(f64_bits("0x1p-1022"), 0x0010000000000000);
+    assert_eq!(f64_bits("0x0.8p-1022"), 0x0008000000000000);
+}
+
+#[test]
+fn float_overflow() {
+    let inf: f64 = Float::infinity();
+    assert_eq!(Scanner::from_str("0x1p1024").float(), Some(inf));
+    assert_eq!(Scanner::from_str("0x1.fffffffffffff8p1023").float(), Some(inf));
+    assert_eq!(f64_bits("0x1.fffffffffffff7p1023"), 0x7FEFFFFFFFFFFFFF);
+    let inf32: f32 = Float::infinity();
+    assert_eq!(Scanner::from_str("0x1p128").float32(), Some(inf32));
+}
+
+#[test]
+fn float32_ties_to_even() {
+    assert_eq!(f32_bits("0x1.000001p0"), 0x3F800000);
+    assert_eq!(f32_bits("0x1.000003p0"), 0x3F800002);
+    assert_eq!(f32_bits("0x1.0000011p0"), 0x3F800001);
+    assert_eq!(f32_bits("0x1p-149"), 1);
+    assert_eq!(f32_bits("0x1p-150"), 0);
+}
+
+#[test]
+fn decimal_floats() {
+    let inputs = ["0.1", "1.5", "123456789.123456789", "2.2250738585072014e-308",
+                  "4.9e-324", "1.7976931348623157e308", "9007199254740993"];
+    for &s in inputs.iter() {
+        assert_eq!(Scanner::from_str(s).float(), Some(s.parse::<f64>().unwrap()));
+    }
+    let inputs = ["0.1", "1.5", "123456789.123456789", "16777217", "3.4028235e38"];
+    for &s in inputs.iter() {
+        assert_eq!(Scanner::from_str(s).float32(), Some(s.parse::<f32>().unwrap()));
+    }
+}
+
+fn separated(s: &str) -> Scanner<Bytes> {
+    let mut s = Scanner::from_str(s);
+    s.set_separators(true);
+    s
+}
+
+#[test]
+fn digit_separators() {
+    assert_eq!(separated("1_000_000").unsigned_integer(), Ok(1000000));
+    assert_eq!(separated("0xFF_FF").unsigned_integer(), Ok(0xFFFF));
+    assert_eq!(separated("-1_000").signed_integer(), Ok(-1000));
+    assert_eq!(separated("1_000.000_1").float(), Some(1000.0001));
+}
+
+#[test]
+fn misplaced_separators() {
+    assert_eq!(separated("_1").unsigned_integer(), Err(NumError::Invalid));
+    assert_eq!(separated("1_").unsigned_integer(), Err(NumError::Invalid));
+    assert_eq!(separated("1__0").unsigned_integer(), Err(NumError::Invalid));
+    assert_eq!(separated("0xF__F").unsigned_integer(), Err(NumError::Invalid));
+    assert_eq!(separated("1_.5").float(), None);
+    assert_eq!(separated("1.5_").float(), None);
+}
+
+#[test]
+fn separators_disabled() {
+    let mut s = Scanner::from_str("1_000");
+    assert_eq!(s.unsigned_integer(), Ok(1));
+    assert_eq!(s.word(), "_000");
+    assert_eq!(Scanner::from_str("_1").unsigned_integer(), Err(NumError::Invalid));
+    let mut s = Scanner::from_str("1_5");
+    assert_eq!(s.float(), Some(1.0));
+    assert_eq!(s.word(), "_5");
+}
@@ -30,7 +30,14 @@ impl UTF8 {
                 None
             };
             self.rem = 0;
-            match (b as char).len_utf8() {
+            // The length of the sequence depends on the lead byte.
+            let len = match b {
+                0xC0...0xDF => 2,
+                0xE0...0xEF => 3,
+                0xF0...0xF7 => 4,
+                _ => 0,
+            };
+            match len {
                 0 => (old, Some(REPLACEMENT)),
                 n => {
                     self.rem = n as u32 - 1;
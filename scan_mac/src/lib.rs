@@ -16,12 +16,15 @@ use rustc::plugin::{Registry};
 use syntax::{ast};
 use syntax::ptr::{P};
 use syntax::ast::{TokenTree, LitStr, Expr, ExprLit, Block, DefaultBlock, ExprLoop,
-                  ExprTup};
-use syntax::codemap::{Span, Pos};
+                  ExprTup, Stmt, Ty, StructDef, StructField_, NamedField, Inherited};
+use syntax::codemap::{Span, Pos, respan};
 use syntax::ext::base::{DummyResult, ExtCtxt, MacResult, MacExpr};
+use syntax::ext::build::{AstBuilder};
 use syntax::fold::{Folder};
 use syntax::parse::{new_parser_from_tts};
-use syntax::parse::token::{Eof};
+use syntax::parse::parser::{LifetimeAndTypesWithoutColons};
+use syntax::parse::token::{Eof, Comma, FatArrow, InternedString};
+use syntax::print::{pprust};
 
 use util::{PeekN, Stream, LeftBrace, LeftBraceBrace, RightBrace, RightBraceBrace,
            Literal, Colon, Space, Token};
@@ -33,11 +36,30 @@ pub fn plugin_registrar(reg: &mut Registry) {
     reg.register_macro("scanln", expand_scanln);
     reg.register_macro("scan",   expand_scan);
     reg.register_macro("readln", expand_readln);
+    reg.register_macro("sscan",  expand_sscan);
+    reg.register_macro("scan_tokens", expand_scan_tokens);
+    reg.register_macro("try_scanln", expand_try_scanln);
+    reg.register_macro("try_scan",   expand_try_scan);
 }
 
-fn parse_macro(cx: &mut ExtCtxt, tts: &[TokenTree]) -> Option<(String, Span)> {
+/// Parses the arguments of a macro of the form `m!("spec")` or `m!(expr, "spec")`.
+///
+/// Both forms can be followed by `=> Path`, the name of the struct that is returned.
+fn parse_macro(cx: &mut ExtCtxt,
+               tts: &[TokenTree]) -> Option<(Option<P<Expr>>, String, Span,
+                                             Option<ast::Path>)> {
     let mut parser = new_parser_from_tts(cx.parse_sess(), cx.cfg(), tts.to_vec());
-    let arg = cx.expander().fold_expr(parser.parse_expr());
+    let mut input = None;
+    let mut arg = parser.parse_expr();
+    if parser.eat(&Comma) {
+        input = Some(arg);
+        arg = parser.parse_expr();
+    }
+    let mut path = None;
+    if parser.eat(&FatArrow) {
+        path = Some(parser.parse_path(LifetimeAndTypesWithoutColons));
+    }
+    let arg = cx.expander().fold_expr(arg);
     let arg_str = match arg.node {
         ExprLit(ref lit) => match lit.node {
             LitStr(ref s, _) => Some(s.get().to_string()),
@@ -50,7 +72,7 @@ fn parse_macro(cx: &mut ExtCtxt, tts: &[TokenTree]) -> Option<(String, Span)> {
         return None;
     }
     match arg_str {
-        Some(s) => Some((s, arg.span)),
+        Some(s) => Some((input, s, arg.span, path)),
         None => {
             cx.span_err(arg.span, "expected string literal");
             None
@@ -63,7 +85,7 @@ struct Parser<'a, 'b: 'a> {
     bytes: &'a str,
     span: Span,
     stream: Stream,
-    args: Vec<Arg>,
+    args: Vec<Spec>,
 }
 
 impl<'a, 'b> Parser<'a, 'b> {
@@ -145,7 +167,218 @@ impl<'a, 'b> Parser<'a, 'b> {
         Ok(())
     }
 
-    fn parse(mut self) -> Result<Vec<Arg>, ()> {
+    /// Parses the modifier after the colon at `i`, e.g., the width in `{u32:4}` or the
+    /// radix in `{u32:x}`.
+    fn modifier(&mut self, i: usize, spec: &mut Spec) -> Result<(), ()> {
+        self.stream.skip_spaces();
+        let (j, len) = match self.stream.next() {
+            Some((j, Literal(len))) => (j, len),
+            Some((j, _)) => return self.err(j, "Expected modifier"),
+            _ => return self.err(i, "Unexpected EOF"),
+        };
+        let text = self.bytes;
+        let m = &text[j..j+len];
+        if m == "fromstr" {
+            match spec.arg {
+                Class(..) => return self.err(j, "Character classes cannot use FromStr"),
+                _ => spec.fromstr = true,
+            }
+            return Ok(());
+        }
+        let radix = match m {
+            "b" => Some(2),
+            "o" => Some(8),
+            "d" => Some(10),
+            "x" => Some(16),
+            _ if m.starts_with("r") => match m[1..].parse::<u32>() {
+                Ok(r) if r >= 2 && r <= 36 => Some(r),
+                _ => return self.err(j, "Expected radix between 2 and 36"),
+            },
+            _ => None,
+        };
+        match (radix, &spec.arg) {
+            (Some(r), &Int(..)) | (Some(r), &Big) => spec.radix = Some(r),
+            (Some(r), &Repeat(ref elem, _))
+            | (Some(r), &List(ref elem, _)) => match **elem {
+                Int(..) | Big => spec.radix = Some(r),
+                _ => return self.err(j, "Radix of a non-integer"),
+            },
+            (Some(_), _) => return self.err(j, "Radix of a non-integer"),
+            (None, _) => match m.parse::<usize>() {
+                Ok(0) | Err(..) => return self.err(j, "Unknown modifier"),
+                Ok(w) => spec.width = Some(w),
+            },
+        }
+        Ok(())
+    }
+
+    /// Parses the brackets that start at `i`.
+    ///
+    /// If they contain a built-in type or a path with at least two segments, they form
+    /// a repetition like `[u32]` or `[u32; 5]`, or a list like `[u32],` if a separator
+    /// follows. Other names are rejected because it's not clear if they are meant as a
+    /// type or a character class. Everything else forms a character class.
+    fn bracket(&mut self, i: usize) -> Result<Arg, ()> {
+        let text = self.bytes;
+        let end = match text[i..].find(']') {
+            Some(e) => i + e,
+            None => return self.class(i),
+        };
+        let inner = &text[i+1..end];
+        let (ty, count) = match inner.find(';') {
+            Some(p) => (inner[..p].trim(), Some(inner[p+1..].trim())),
+            None => (inner.trim(), None),
+        };
+        let name = ty.len() > 0 && is_ident(ty) && match count {
+            Some(c) => c.parse::<usize>().is_ok(),
+            None => true,
+        };
+        let elem = match builtin(ty) {
+            Some(arg) => arg,
+            None => match path_segments(ty) {
+                Some(ref s) if s.len() > 1 => match path_arg(s.clone()) {
+                    Some(arg) => arg,
+                    None => return self.class(i),
+                },
+                _ if name => {
+                    return self.err(i, "Expected a path like `[self::Point]` or a range \
+                                        like `[a-z]`");
+                },
+                _ => return self.class(i),
+            },
+        };
+        let count = match count {
+            Some(c) => match c.parse::<usize>() {
+                Ok(0) | Err(..) => return self.err(i, "Expected number of values"),
+                Ok(c) => Some(c),
+            },
+            None => None,
+        };
+        // A separator follows the brackets up to the next modifier or the end of the
+        // specifier.
+        let rest = &text[end+1..];
+        let sep_len = rest.find(|c: char| c == ':' || c == '}').unwrap_or(rest.len());
+        let sep_end = end + 1 + sep_len;
+        let sep = text[end+1..sep_end].trim();
+        // Go back to the token at `i` which may extend past the brackets.
+        self.stream.step_back();
+        if sep.len() == 0 {
+            self.stream.seek(end + 1);
+            return Ok(Repeat(Box::new(elem), count));
+        }
+        if count.is_some() {
+            return self.err(i, "Lists with a separator cannot have a length");
+        }
+        self.stream.seek(sep_end);
+        Ok(List(Box::new(elem), sep.to_string()))
+    }
+
+    /// Parses a character class like `[a-z_]` or `[^=]` that starts at `i`.
+    ///
+    /// A `]` right after the opening bracket or `^` belongs to the class. A `-` between
+    /// two characters forms a range.
+    fn class(&mut self, i: usize) -> Result<Arg, ()> {
+        let text = self.bytes;
+        let bytes = text.as_bytes();
+        let mut k = i + 1;
+        let negated = k < bytes.len() && bytes[k] == b'^';
+        if negated {
+            k += 1;
+        }
+        let start = k;
+        if k < bytes.len() && bytes[k] == b']' {
+            k += 1;
+        }
+        while k < bytes.len() && bytes[k] != b']' {
+            k += 1;
+        }
+        if k == bytes.len() {
+            return self.err(i, "Unterminated character class");
+        }
+        let mut set = [0u64; 4];
+        let mut m = start;
+        while m < k {
+            let lo = bytes[m];
+            let hi = if m + 2 < k && bytes[m + 1] == b'-' {
+                m += 2;
+                bytes[m]
+            } else {
+                lo
+            };
+            if hi < lo {
+                return self.err(m, "Invalid range");
+            }
+            for b in range(lo as usize, hi as usize + 1) {
+                set[b >> 6] |= 1 << (b & 63);
+            }
+            m += 1;
+        }
+        if negated {
+            for w in set.iter_mut() {
+                *w = !*w;
+            }
+        }
+        // Go back to the token at `i` which may extend past the class.
+        self.stream.step_back();
+        self.stream.seek(k + 1);
+        Ok(Class(set, text[i..k+1].to_string()))
+    }
+
+    /// Checks if the literal before the next token is the name of a value.
+    ///
+    /// This is the case if the next token is a colon that is followed by a type and
+    /// not by a modifier or a second colon, as in `{year:u32}`, `{u32:4}` and
+    /// `{geo::Point}` respectively.
+    fn is_name(&self) -> bool {
+        let colon = match self.stream.peek(0) {
+            Some((k, Colon)) => k,
+            _ => return false,
+        };
+        let mut n = 1;
+        loop {
+            match self.stream.peek(n) {
+                Some((_, Space)) => n += 1,
+                _ => break,
+            }
+        }
+        match self.stream.peek(n) {
+            Some((k, Colon)) => k != colon + 1,
+            Some((k, Literal(len))) => !is_modifier(&self.bytes[k..k+len]),
+            _ => true,
+        }
+    }
+
+    /// Parses the path of a type that implements `FromScan`. The first segment is the
+    /// literal at `j`.
+    fn path(&mut self, mut j: usize, mut len: usize) -> Result<Arg, ()> {
+        let text = self.bytes;
+        let mut segments = vec!();
+        loop {
+            let segment = &text[j..j+len];
+            if !is_ident(segment) {
+                return self.err(j, "Unknown type");
+            }
+            segments.push(segment.to_string());
+            // `::` continues the path.
+            match (self.stream.peek(0), self.stream.peek(1), self.stream.peek(2)) {
+                (Some((a, Colon)), Some((b, Colon)), Some((c, Literal(l))))
+                        if b == a + 1 && c == b + 1 => {
+                    self.stream.next();
+                    self.stream.next();
+                    self.stream.next();
+                    j = c;
+                    len = l;
+                },
+                _ => break,
+            }
+        }
+        match path_arg(segments) {
+            Some(arg) => Ok(arg),
+            None => self.err(j, "Unknown type"),
+        }
+    }
+
+    fn parse(mut self) -> Result<Vec<Spec>, ()> {
         try!(self.tokenize());
 
         loop {
@@ -157,23 +390,23 @@ impl<'a, 'b> Parser<'a, 'b> {
             macro_rules! push_lit {
                 ($l:expr) => {
                     match self.args.pop() {
-                        Some(Lit(mut v)) => {
+                        Some(Spec { arg: Lit(mut v), .. }) => {
                             v.push_str($l);
-                            self.args.push(Lit(v));
+                            self.args.push(Spec::new(Lit(v)));
                         },
                         Some(x) => {
                             self.args.push(x);
-                            self.args.push(Lit($l.to_string()));
+                            self.args.push(Spec::new(Lit($l.to_string())));
                         },
                         _ => {
-                            self.args.push(Lit($l.to_string()));
+                            self.args.push(Spec::new(Lit($l.to_string())));
                         },
                     }
                 }
             };
 
             match t {
-                Space           => self.args.push(Whitespace),
+                Space           => self.args.push(Spec::new(Whitespace)),
                 Literal(len)    => {
                     push_lit!(&self.bytes[i..i+len]);
                 },
@@ -183,28 +416,63 @@ impl<'a, 'b> Parser<'a, 'b> {
                 RightBrace      => try!(self.err(i, "Unexpected token")),
                 LeftBrace => {
                     self.stream.skip_spaces();
-                    let (j, len) = match self.stream.next() {
+                    let (mut j, mut len) = match self.stream.next() {
                         Some((j, Literal(len))) => (j, len),
                         Some((j, _)) => try!(self.err(j, "Expected type")),
                         _ => try!(self.err(i, "Unexpected EOF")),
                     };
-                    let arg = match &self.bytes[j..j+len] {
-                        "i8"  => Int(I8),
-                        "u8"  => Int(U8), 
-                        "i16" => Int(I16), 
-                        "u16" => Int(U16), 
-                        "i32" => Int(I32), 
-                        "u32" => Int(U32), 
-                        "i64" => Int(I64), 
-                        "u64" => Int(U64), 
-                        "i"   => Int(I), 
-                        "u"   => Int(U),
-                        "f32" => Float(false),
-                        "f64" => Float(true),
-                        "s"   => Strin,
-                        _ => try!(self.err(j, "Unknown type")),
+                    // `{year:u32}` names the value.
+                    let mut name = None;
+                    let text = self.bytes;
+                    let first = &text[j..j+len];
+                    if is_ident(first) && builtin(first).is_none() && self.is_name() {
+                        let (k, _) = self.stream.next().unwrap();
+                        name = Some(first.to_string());
+                        self.stream.skip_spaces();
+                        match self.stream.next() {
+                            Some((m, Literal(l))) => {
+                                j = m;
+                                len = l;
+                            },
+                            Some((m, _)) => try!(self.err(m, "Expected type")),
+                            _ => try!(self.err(k, "Unexpected EOF")),
+                        }
+                    }
+                    // `{*u32}` parses a value without returning it.
+                    let skip = self.bytes.as_bytes()[j] == b'*';
+                    if skip {
+                        j += 1;
+                        len -= 1;
+                        if len == 0 {
+                            try!(self.err(j, "Expected type"));
+                        }
+                    }
+                    let arg = if self.bytes.as_bytes()[j] == b'[' {
+                        try!(self.bracket(j))
+                    } else {
+                        match builtin(&self.bytes[j..j+len]) {
+                            Some(arg) => arg,
+                            None => try!(self.path(j, len)),
+                        }
                     };
-                    self.args.push(arg);
+                    if skip && name.is_some() {
+                        try!(self.err(j, "Skipped values cannot be named"));
+                    }
+                    let mut spec = Spec::new(arg);
+                    spec.skip = skip;
+                    spec.name = name;
+                    loop {
+                        self.stream.skip_spaces();
+                        match self.stream.next() {
+                            Some((k, Colon)) => try!(self.modifier(k, &mut spec)),
+                            Some(_) => {
+                                self.stream.step_back();
+                                break;
+                            },
+                            None => break,
+                        }
+                    }
+                    self.args.push(spec);
                     self.stream.skip_spaces();
                     match self.stream.next() {
                         Some((_, RightBrace)) => { },
@@ -229,13 +497,18 @@ enum IntType {
     U32,
     I64,
     U64,
+    I128,
+    U128,
     I,
     U,
 }
 
 impl IntType {
+    /// Returns a closure that converts the parsed `U128` or `I128` to this type or fails
+    /// with `NumError::Overflow`.
     fn map<'a>(self, cx: &ExtCtxt<'a>) -> P<Expr> {
         let ty = match self {
+            I128 | U128 => return quote_expr!(cx, |v| Ok(v)),
             I8  => quote_expr!(cx, i8),
             U8  => quote_expr!(cx, u8),
             I16 => quote_expr!(cx, i16),
@@ -247,13 +520,96 @@ impl IntType {
             I   => quote_expr!(cx, isize),
             U   => quote_expr!(cx, usize),
         };
-        quote_expr!(cx, |v| v as $ty)
+        if self.signed() {
+            quote_expr!(cx, |v: ::scan::I128| {
+                let min: $ty = ::std::num::Int::min_value();
+                let max: $ty = ::std::num::Int::max_value();
+                match v.to_i64() {
+                    Some(v) if v >= min as i64 && v <= max as i64 => Ok(v as $ty),
+                    _ => Err(::scan::NumError::Overflow),
+                }
+            })
+        } else {
+            quote_expr!(cx, |v: ::scan::U128| {
+                let max: $ty = ::std::num::Int::max_value();
+                match v.to_u64() {
+                    Some(v) if v <= max as u64 => Ok(v as $ty),
+                    _ => Err(::scan::NumError::Overflow),
+                }
+            })
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            I8  => "i8",
+            U8  => "u8",
+            I16 => "i16",
+            U16 => "u16",
+            I32 => "i32",
+            U32 => "u32",
+            I64 => "i64",
+            U64 => "u64",
+            I128 => "i128",
+            U128 => "u128",
+            I   => "isize",
+            U   => "usize",
+        }
     }
 
     fn signed(self) -> bool {
         match self {
-            I8 | I16 | I32 | I64 | I => true,
-            U8 | U16 | U32 | U64 | U => false,
+            I8 | I16 | I32 | I64 | I128 | I => true,
+            U8 | U16 | U32 | U64 | U128 | U => false,
+        }
+    }
+}
+
+/// The kind of input a macro reads from.
+#[derive(Copy, PartialEq)]
+enum Kind {
+    /// `scan!` and `scanln!` read a line from stdin or the passed scanner.
+    Line,
+    /// `scan_tokens!` reads from stdin or the passed scanner and treats newlines as
+    /// whitespace.
+    Tokens,
+    /// `sscan!` reads from the passed in-memory buffer.
+    Memory,
+}
+
+/// Where the expanded code reads its input from.
+enum Input {
+    Stdin,
+    Tokens,
+    Memory(P<Expr>),
+    /// A scanner passed by the user.
+    Scanner(P<Expr>),
+}
+
+/// A specifier and its modifiers.
+struct Spec {
+    arg: Arg,
+    /// The maximum number of bytes the specifier may consume.
+    width: Option<usize>,
+    /// The radix of an integer.
+    radix: Option<u32>,
+    /// If the value is parsed but not returned.
+    skip: bool,
+    /// The name of the field that contains the value.
+    name: Option<String>,
+    /// If the value is parsed from a word with `FromStr`.
+    fromstr: bool,
+}
+
+impl Spec {
+    fn new(arg: Arg) -> Spec {
+        Spec {
+            arg: arg,
+            width: None,
+            radix: None,
+            skip: false,
+            name: None,
+            fromstr: false,
         }
     }
 }
@@ -264,25 +620,284 @@ enum Arg {
     Int(IntType),
     Float(bool),
     Strin,
+    /// An integer of arbitrary size.
+    Big,
+    Char,
+    Bool,
+    /// A non-empty string of the bytes in the set, and the class as written.
+    Class([u64; 4], String),
+    /// A type that implements `FromScan`. The path is global if the flag is set.
+    Path(bool, Vec<String>),
+    /// Values separated by whitespace, either until the end of the line or the given
+    /// number of values.
+    Repeat(Box<Arg>, Option<usize>),
+    /// Values separated by the literal.
+    List(Box<Arg>, String),
+}
+
+/// Checks if `s` is an identifier that can be used as the name of a value.
+fn is_ident(s: &str) -> bool {
+    s.chars().enumerate().all(|(i, c)| match c {
+        'a'...'z' | 'A'...'Z' | '_' => true,
+        '0'...'9' => i > 0,
+        _ => false,
+    })
+}
+
+/// Checks if `s` is a modifier like the `4` in `{u32:4}` or the `x` in `{u32:x}`.
+fn is_modifier(s: &str) -> bool {
+    match s {
+        "b" | "o" | "d" | "x" | "fromstr" => true,
+        _ if s.starts_with("r") => s[1..].parse::<u32>().is_ok(),
+        _ => s.parse::<usize>().is_ok(),
+    }
+}
+
+/// Returns the type of the value that `arg` parses.
+fn value_type(cx: &ExtCtxt, sp: Span, arg: &Arg) -> P<Ty> {
+    match *arg {
+        Int(I128) => quote_ty!(cx, ::scan::I128),
+        Int(U128) => quote_ty!(cx, ::scan::U128),
+        Int(ty) => {
+            let ty = cx.ident_of(ty.name());
+            quote_ty!(cx, $ty)
+        },
+        Float(true)  => quote_ty!(cx, f64),
+        Float(false) => quote_ty!(cx, f32),
+        Strin | Class(..) => quote_ty!(cx, String),
+        Big  => quote_ty!(cx, ::scan::BigInt),
+        Char => quote_ty!(cx, char),
+        Bool => quote_ty!(cx, bool),
+        Repeat(ref elem, None) => {
+            let elem = value_type(cx, sp, &**elem);
+            quote_ty!(cx, Vec<$elem>)
+        },
+        Repeat(ref elem, Some(count)) => {
+            let elem = value_type(cx, sp, &**elem);
+            quote_ty!(cx, [$elem; $count])
+        },
+        List(ref elem, _) => {
+            let elem = value_type(cx, sp, &**elem);
+            quote_ty!(cx, Vec<$elem>)
+        },
+        Path(global, ref segments) => {
+            let idents = segments.iter().map(|s| cx.ident_of(&s[])).collect();
+            let path = if global {
+                cx.path_global(sp, idents)
+            } else {
+                cx.path(sp, idents)
+            };
+            cx.ty_path(path)
+        },
+        Lit(..) | Whitespace => unreachable!(),
+    }
+}
+
+/// Returns an expression that parses the value of `arg` and returns a `Result`.
+///
+/// If `fromstr` is set, the value is parsed with `FromStr`.
+fn read_expr(cx: &ExtCtxt, sp: Span, arg: &Arg, fromstr: bool) -> P<Expr> {
+    if fromstr {
+        let ty = value_type(cx, sp, arg);
+        return quote_expr!(cx, pb.parse::<$ty>());
+    }
+    match *arg {
+        Int(ty) => {
+            let map = ty.map(cx);
+            if ty.signed() {
+                quote_expr!(cx, pb.signed_integer128().and_then($map))
+            } else {
+                quote_expr!(cx, pb.unsigned_integer128().and_then($map))
+            }
+        },
+        Big => quote_expr!(cx, pb.big_integer()),
+        Float(true)  => quote_expr!(cx, pb.float().ok_or(())),
+        Float(false) => quote_expr!(cx, pb.float32().ok_or(())),
+        Char => quote_expr!(cx, pb.character().ok_or(())),
+        Bool => quote_expr!(cx, pb.boolean().ok_or(())),
+        Strin => quote_expr!(cx, {
+            let r: Result<String, ()> = Ok(pb.word());
+            r
+        }),
+        Path(..) => {
+            let ty = value_type(cx, sp, arg);
+            quote_expr!(cx, pb.scan::<$ty>())
+        },
+        Class(set, _) => {
+            let (s0, s1, s2, s3) = (set[0], set[1], set[2], set[3]);
+            quote_expr!(cx, {
+                let set: [u64; 4] = [$s0, $s1, $s2, $s3];
+                let s = pb.take_while(|b| {
+                    set[(b >> 6) as usize] & (1 << (b & 63) as usize) != 0
+                });
+                if s.len() == 0 { Err(()) } else { Ok(s) }
+            })
+        },
+        Repeat(..) | List(..) | Lit(..) | Whitespace => unreachable!(),
+    }
+}
+
+/// Returns an expression that describes what `arg` expected if `read_expr` failed
+/// with the error `_e`.
+fn expected_expr(cx: &ExtCtxt, sp: Span, arg: &Arg, fromstr: bool) -> Option<P<Expr>> {
+    let expected = match *arg {
+        Repeat(ref elem, _) | List(ref elem, _) => {
+            return expected_expr(cx, sp, &**elem, fromstr);
+        },
+        _ if fromstr => {
+            let name = pprust::ty_to_string(&*value_type(cx, sp, arg));
+            let name = &name[];
+            quote_expr!(cx, ::scan::Expected::Value($name.to_string()))
+        },
+        Int(ty) => {
+            let name = ty.name();
+            quote_expr!(cx,
+                match _e {
+                    ::scan::NumError::Invalid => ::scan::Expected::Integer,
+                    ::scan::NumError::Overflow => ::scan::Expected::Range($name),
+                }
+            )
+        },
+        Big => quote_expr!(cx, ::scan::Expected::Integer),
+        Class(_, ref c) => {
+            let c = &c[];
+            quote_expr!(cx, ::scan::Expected::Class($c.to_string()))
+        },
+        Float(..) => quote_expr!(cx, ::scan::Expected::Float),
+        Char => quote_expr!(cx, ::scan::Expected::Character),
+        Bool => quote_expr!(cx, ::scan::Expected::Boolean),
+        Path(..) => quote_expr!(cx, _e),
+        Strin | Lit(..) | Whitespace => return None,
+    };
+    Some(expected)
+}
+
+/// Splits a path like `geo::Point` into its segments.
+fn path_segments(s: &str) -> Option<Vec<String>> {
+    let mut segments = vec!();
+    let mut rest = s;
+    loop {
+        let end = rest.find(':').unwrap_or(rest.len());
+        let segment = &rest[..end];
+        if segment.len() == 0 || !is_ident(segment) {
+            return None;
+        }
+        segments.push(segment.to_string());
+        if end == rest.len() {
+            return Some(segments);
+        }
+        if !rest[end..].starts_with("::") {
+            return None;
+        }
+        rest = &rest[end+2..];
+    }
+}
+
+/// Creates the argument for a type with the path `segments`.
+///
+/// A leading `crate` segment refers to the root of the crate.
+fn path_arg(segments: Vec<String>) -> Option<Arg> {
+    if segments[0] == "crate" {
+        match segments.len() {
+            1 => None,
+            _ => Some(Path(true, segments[1..].to_vec())),
+        }
+    } else {
+        Some(Path(false, segments))
+    }
+}
+
+/// Checks if the value of `arg` is known to implement `Debug`.
+///
+/// Custom types might not implement it and arrays only implement it up to a length of
+/// 32.
+fn is_debug(arg: &Arg) -> bool {
+    match *arg {
+        Path(..) => false,
+        Repeat(_, Some(count)) if count > 32 => false,
+        Repeat(ref elem, _) | List(ref elem, _) => is_debug(&**elem),
+        _ => true,
+    }
+}
+
+/// Returns the argument for the built-in type `name`.
+fn builtin(name: &str) -> Option<Arg> {
+    let arg = match name {
+        "i8"  => Int(I8),
+        "u8"  => Int(U8),
+        "i16" => Int(I16),
+        "u16" => Int(U16),
+        "i32" => Int(I32),
+        "u32" => Int(U32),
+        "i64" => Int(I64),
+        "u64" => Int(U64),
+        "i128" => Int(I128),
+        "u128" => Int(U128),
+        "i"   => Int(I),
+        "u"   => Int(U),
+        "f32" => Float(false),
+        "f64" => Float(true),
+        "s"   => Strin,
+        "big" => Big,
+        "c"   => Char,
+        "bool" => Bool,
+        _ => return None,
+    };
+    Some(arg)
 }
 
 fn expand_scanln<'a>(cx: &'a mut ExtCtxt, sp: Span,
                       tts: &[TokenTree]) -> Box<MacResult+'static> {
-    expand_scan_common(cx, sp, tts, true)
+    expand_scan_common(cx, sp, tts, true, Kind::Line, false)
 }
 
 fn expand_scan<'a>(cx: &'a mut ExtCtxt, sp: Span,
                    tts: &[TokenTree]) -> Box<MacResult+'static> {
-    expand_scan_common(cx, sp, tts, false)
+    expand_scan_common(cx, sp, tts, false, Kind::Line, false)
+}
+
+fn expand_sscan<'a>(cx: &'a mut ExtCtxt, sp: Span,
+                    tts: &[TokenTree]) -> Box<MacResult+'static> {
+    expand_scan_common(cx, sp, tts, false, Kind::Memory, false)
+}
+
+fn expand_scan_tokens<'a>(cx: &'a mut ExtCtxt, sp: Span,
+                          tts: &[TokenTree]) -> Box<MacResult+'static> {
+    expand_scan_common(cx, sp, tts, false, Kind::Tokens, false)
+}
+
+fn expand_try_scanln<'a>(cx: &'a mut ExtCtxt, sp: Span,
+                          tts: &[TokenTree]) -> Box<MacResult+'static> {
+    expand_scan_common(cx, sp, tts, true, Kind::Line, true)
+}
+
+fn expand_try_scan<'a>(cx: &'a mut ExtCtxt, sp: Span,
+                       tts: &[TokenTree]) -> Box<MacResult+'static> {
+    expand_scan_common(cx, sp, tts, false, Kind::Line, true)
 }
 
+/// Expands a scanning macro.
+///
+/// If `fallible` is `true`, the macro returns a `Result` instead of a tuple of `Option`s.
 fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
-                          drop_line: bool) -> Box<MacResult+'static> {
-    let (lit, span) = match parse_macro(cx, tts) {
+                          drop_line: bool, kind: Kind,
+                          fallible: bool) -> Box<MacResult+'static> {
+    let (input, lit, span, path) = match parse_macro(cx, tts) {
         Some(x) => x,
         None => return DummyResult::expr(sp),
     };
 
+    let input = match (input, kind) {
+        (Some(e), Kind::Memory) => Input::Memory(e),
+        (Some(e), _) => Input::Scanner(e),
+        (None, Kind::Line) => Input::Stdin,
+        (None, Kind::Tokens) => Input::Tokens,
+        (None, Kind::Memory) => {
+            cx.span_err(span, "expected input argument");
+            return DummyResult::expr(sp);
+        },
+    };
+
     let args = match Parser::new(cx, &lit[], span).parse() {
         Ok(args) => args,
         _ => return DummyResult::expr(sp),
@@ -293,16 +908,33 @@ fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
     let mut decls = vec!();
     let mut retvs = vec!();
     let mut tupel_vals = vec!();
+    // The names and types of the returned values.
+    let mut fields = vec!();
+    // If all returned values implement `Debug`.
+    let mut debug = true;
 
-    retvs.push(quote_stmt!(cx,
-        let mut pb = ::scan::stdin($drop_line);
-    ));
+    let scanner = match input {
+        Input::Stdin => quote_stmt!(cx,
+            let mut pb = ::scan::stdin($drop_line);
+        ),
+        Input::Tokens => quote_stmt!(cx,
+            let mut pb = ::scan::tokens();
+        ),
+        Input::Memory(ref e) => quote_stmt!(cx,
+            let mut pb = ::scan::Scanner::from_bytes(::std::convert::AsRef::as_ref(&*$e));
+        ),
+        Input::Scanner(ref e) => quote_stmt!(cx,
+            let pb = &mut $e;
+        ),
+    };
 
-    for arg in args.into_iter() {
+    for (n, spec) in args.into_iter().enumerate() {
+        let arg = spec.arg;
         let i = decls.len();
         let ident = cx.ident_of(&format!("a{}", i)[]);
         match arg {
-            Int(..) | Float(..) | Strin => {
+            Lit(..) | Whitespace => { },
+            _ => {
                 decls.push(quote_stmt!(cx,
                     let mut $ident = None;
                 ));
@@ -310,16 +942,60 @@ fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
                 decls.push(quote_stmt!(cx,
                     let _ = $ident;
                 ));
-                tupel_vals.push(quote_expr!(cx, $ident));
+                if !spec.skip {
+                    tupel_vals.push(quote_expr!(cx, $ident));
+                    let ty = value_type(cx, sp, &arg);
+                    let ty = if fallible { ty } else { quote_ty!(cx, Option<$ty>) };
+                    fields.push((spec.name.clone(), ty));
+                    debug &= !spec.fromstr && is_debug(&arg);
+                }
+            },
+        }
+        let expected = match arg {
+            Lit(ref v) => {
+                let ss = &v[];
+                Some(quote_expr!(cx, ::scan::Expected::Literal($ss.to_string())))
             },
-            _ => { },
+            Whitespace => None,
+            _ => expected_expr(cx, sp, &arg, spec.fromstr),
+        };
+        let fail = match expected {
+            // Keep the error returned by `FromStr`.
+            Some(ref expected) if fallible && spec.fromstr => quote_stmt!(cx, {
+                let mut e = pb.mismatch($n, $expected);
+                e.parse_error = Some(_e);
+                err = Some(e);
+                break;
+            }),
+            Some(ref expected) if fallible => quote_stmt!(cx, {
+                err = Some(pb.mismatch($n, $expected));
+                break;
+            }),
+            _ => quote_stmt!(cx, break;),
+        };
+        match spec.width {
+            Some(w) => retvs.push(quote_stmt!(cx, pb.set_width(Some($w));)),
+            None => { },
+        }
+        match spec.radix {
+            Some(r) => retvs.push(quote_stmt!(cx, pb.set_radix(Some($r));)),
+            None => { },
         }
+        // Resets the scanner settings after the value has been read.
+        let reset = |retvs: &mut Vec<P<Stmt>>| {
+            if spec.width.is_some() {
+                retvs.push(quote_stmt!(cx, pb.set_width(None);));
+            }
+            if spec.radix.is_some() {
+                retvs.push(quote_stmt!(cx, pb.set_radix(None);));
+            }
+        };
         match arg {
             Lit(ref v) => {
                 let ss = &v[];
                 retvs.push(quote_stmt!(cx,
                     if pb.literal($ss).is_none() {
-                        break;
+                        $fail
                     }
                 ));
             },
@@ -328,37 +1004,134 @@ fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
                     pb.whitespace();
                 ));
             },
-            Int(ty) => {
-                let map = ty.map(cx);
-                if ty.signed() {
-                    retvs.push(quote_stmt!(cx,
-                        $ident = pb.signed_integer().map($map);
-                    ));
-                } else {
-                    retvs.push(quote_stmt!(cx,
-                        $ident = pb.unsigned_integer().map($map);
-                    ));
-                }
+            Repeat(ref elem, None) => {
+                let read = read_expr(cx, sp, &**elem, spec.fromstr);
+                retvs.push(quote_stmt!(cx,
+                    let mut _v = Vec::new();
+                ));
+                retvs.push(quote_stmt!(cx,
+                    let mut _err = None;
+                ));
+                retvs.push(quote_stmt!(cx,
+                    loop {
+                        pb.whitespace();
+                        if pb.peek().is_none() {
+                            break;
+                        }
+                        let _start = pb.consumed();
+                        match $read {
+                            Ok(v) => _v.push(v),
+                            Err(e) => {
+                                _err = Some(e);
+                                break;
+                            },
+                        }
+                        // Values that consume no input would be read forever.
+                        if pb.consumed() == _start {
+                            break;
+                        }
+                    }
+                ));
+                reset(&mut retvs);
                 retvs.push(quote_stmt!(cx,
-                    if $ident.is_none() {
-                        break;
+                    match _err {
+                        Some(_e) => { $fail }
+                        None => $ident = Some(_v),
                     }
                 ));
             },
-            Float(long) => {
-                if long {
-                    retvs.push(quote_stmt!(cx,
-                        $ident = pb.float();
-                    ));
-                } else {
-                    retvs.push(quote_stmt!(cx,
-                        $ident = pb.float().map(|v| as f32);
-                    ));
-                }
+            Repeat(ref elem, Some(count)) => {
+                let read = read_expr(cx, sp, &**elem, spec.fromstr);
+                retvs.push(quote_stmt!(cx,
+                    let mut _v = Vec::with_capacity($count);
+                ));
+                retvs.push(quote_stmt!(cx,
+                    let mut _err = None;
+                ));
+                retvs.push(quote_stmt!(cx,
+                    for _ in range(0, $count) {
+                        pb.whitespace();
+                        match $read {
+                            Ok(v) => _v.push(v),
+                            Err(e) => {
+                                _err = Some(e);
+                                break;
+                            },
+                        }
+                    }
+                ));
+                reset(&mut retvs);
+                let elems = range(0, count).map(|_| quote_expr!(cx, _it.next().unwrap()));
+                let array = cx.expr_vec(sp, elems.collect());
+                retvs.push(quote_stmt!(cx,
+                    match _err {
+                        Some(_e) => { $fail }
+                        None => {
+                            let mut _it = _v.into_iter();
+                            $ident = Some($array);
+                        },
+                    }
+                ));
             },
-            Strin => {
+            List(ref elem, ref sep) => {
+                let sep = &sep[];
+                // Strings end before the separator.
+                let stop = sep.as_bytes()[0];
+                let read = match **elem {
+                    _ if spec.fromstr => {
+                        let ty = value_type(cx, sp, &**elem);
+                        quote_expr!(cx, pb.parse_before::<$ty>($stop))
+                    },
+                    Strin => quote_expr!(cx, {
+                        let r: Result<String, ()> = Ok(pb.word_before($stop));
+                        r
+                    }),
+                    ref elem => read_expr(cx, sp, elem, false),
+                };
+                retvs.push(quote_stmt!(cx,
+                    let mut _v = Vec::new();
+                ));
+                retvs.push(quote_stmt!(cx,
+                    let mut _err = None;
+                ));
                 retvs.push(quote_stmt!(cx,
-                    $ident = Some(pb.word());
+                    pb.whitespace();
+                ));
+                retvs.push(quote_stmt!(cx,
+                    if pb.peek().is_some() {
+                        loop {
+                            match $read {
+                                Ok(v) => _v.push(v),
+                                Err(e) => {
+                                    _err = Some(e);
+                                    break;
+                                },
+                            }
+                            if pb.separator($sep).is_none() {
+                                break;
+                            }
+                        }
+                    }
+                ));
+                reset(&mut retvs);
+                retvs.push(quote_stmt!(cx,
+                    match _err {
+                        Some(_e) => { $fail }
+                        None => $ident = Some(_v),
+                    }
+                ));
+            },
+            _ => {
+                let read = read_expr(cx, sp, &arg, spec.fromstr);
+                retvs.push(quote_stmt!(cx,
+                    let _r = $read;
+                ));
+                reset(&mut retvs);
+                retvs.push(quote_stmt!(cx,
+                    match _r {
+                        Ok(v) => $ident = Some(v),
+                        Err(_e) => { $fail }
+                    }
                 ));
             },
         }
@@ -380,21 +1153,109 @@ fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
     });
 
 
-    let tupel = if tupel_vals.len() != 1 {
+    if fallible {
+        tupel_vals = tupel_vals.into_iter().map(|v| quote_expr!(cx, $v.unwrap())).collect();
+    }
+    let named = fields.iter().any(|&(ref name, _)| name.is_some());
+    if named && fields.iter().any(|&(ref name, _)| name.is_none()) {
+        cx.span_err(span, "either all or no values must be named");
+        return DummyResult::expr(sp);
+    }
+    if path.is_some() && !named {
+        cx.span_err(span, "a struct can only be returned if the values are named");
+        return DummyResult::expr(sp);
+    }
+    // The definition of the returned struct if it has no name.
+    let mut item = None;
+    let tupel = if named {
+        let mut defs = vec!();
+        let mut inits = vec!();
+        for ((name, ty), val) in fields.into_iter().zip(tupel_vals.into_iter()) {
+            let name = cx.ident_of(&name.unwrap()[]);
+            defs.push(respan(sp, StructField_ {
+                kind: NamedField(name, Inherited),
+                id: ast::DUMMY_NODE_ID,
+                ty: ty,
+                attrs: vec!(),
+            }));
+            inits.push(cx.field_imm(sp, name, val));
+        }
+        match path {
+            Some(path) => cx.expr_struct(sp, path, inits),
+            None => {
+                let name = cx.ident_of("Scanned");
+                let def = StructDef { fields: defs, ctor_id: None };
+                let derive = cx.attribute(sp, cx.meta_list(sp,
+                    InternedString::new("derive"),
+                    vec!(cx.meta_word(sp, InternedString::new("Debug")))));
+                item = Some(cx.item_struct(sp, name, def).map(|mut i| {
+                    if debug {
+                        i.attrs.push(derive);
+                    }
+                    i
+                }));
+                cx.expr_struct_ident(sp, name, inits)
+            },
+        }
+    } else if tupel_vals.len() != 1 {
         P(Expr {
             id: ast::DUMMY_NODE_ID,
             node: ExprTup(tupel_vals),
             span: sp,
         })
     } else {
-        quote_expr!(cx, a0)
+        tupel_vals.pop().unwrap()
+    };
+    let tupel = if fallible {
+        quote_expr!(cx,
+            match err {
+                Some(e) => Err(e),
+                None => Ok($tupel),
+            }
+        )
+    } else {
+        tupel
+    };
+    let tupel = match input {
+        Input::Memory(..) => quote_expr!(cx, ($tupel, pb.consumed())),
+        _ => tupel,
     };
 
     let mut statements = vec!();
+    match item {
+        Some(item) => statements.push(cx.stmt_item(sp, item)),
+        None => { },
+    }
+    statements.push(scanner);
+    if fallible {
+        statements.push(quote_stmt!(cx,
+            let mut err: Option<::scan::ScanError> = None;
+        ));
+    }
     for d in decls.into_iter() {
         statements.push(quote_stmt!(cx, $d));
     }
+    match input {
+        Input::Scanner(..) => statements.push(quote_stmt!(cx, pb.resume();)),
+        _ => { },
+    }
+    // A passed scanner reads tokens only while this macro runs.
+    let tokens = match input {
+        Input::Scanner(..) => kind == Kind::Tokens,
+        _ => false,
+    };
+    if tokens {
+        statements.push(quote_stmt!(cx, let _multiline = pb.multiline();));
+        statements.push(quote_stmt!(cx, pb.set_multiline(true);));
+    }
     statements.push(quote_stmt!(cx, $looop));
+    if tokens {
+        statements.push(quote_stmt!(cx, pb.set_multiline(_multiline);));
+    }
+    match input {
+        Input::Scanner(..) if drop_line => statements.push(quote_stmt!(cx, pb.skip_line();)),
+        _ => { },
+    }
 
     let final_block = P(Block {
         stmts: statements,
@@ -408,16 +1269,26 @@ fn expand_scan_common<'a>(cx: &'a mut ExtCtxt, sp: Span, tts: &[TokenTree],
 }
 
 fn expand_readln<'a>(cx: &'a mut ExtCtxt, sp: Span,
-                     _tts: &[TokenTree]) -> Box<MacResult+'static> {
-    let decl = quote_stmt!(cx,
-        let mut pb = ::scan::stdin(false);
-    );
+                     tts: &[TokenTree]) -> Box<MacResult+'static> {
+    let mut stmts = vec!();
+    if tts.len() > 0 {
+        let mut parser = new_parser_from_tts(cx.parse_sess(), cx.cfg(), tts.to_vec());
+        let scanner = parser.parse_expr();
+        if parser.token != Eof {
+            cx.span_err(parser.span, "unexpected token");
+            return DummyResult::expr(sp);
+        }
+        stmts.push(quote_stmt!(cx, let pb = &mut $scanner;));
+        stmts.push(quote_stmt!(cx, pb.resume();));
+    } else {
+        stmts.push(quote_stmt!(cx, let mut pb = ::scan::stdin(false);));
+    }
     let res = quote_expr!(cx,
         pb.line()
     );
 
     let final_block = P(Block {
-        stmts: vec!(decl),
+        stmts: stmts,
         expr: Some(res),
         id: ast::DUMMY_NODE_ID,
         rules: DefaultBlock,
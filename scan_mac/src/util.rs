@@ -90,6 +90,40 @@ impl Stream {
             self.pos -= 1;
         }
     }
+
+    /// Returns the token `n` positions ahead without consuming it.
+    pub fn peek(&self, n: usize) -> Option<(usize, Token)> {
+        self.tokens.get(self.pos + n).map(|&t| t)
+    }
+
+    /// Skips the tokens before the byte at `pos`.
+    ///
+    /// A token that starts before `pos` and ends after it is replaced by the literal
+    /// text from `pos` to its end.
+    pub fn seek(&mut self, pos: usize) {
+        while self.pos < self.tokens.len() {
+            let (i, t) = self.tokens[self.pos];
+            if i >= pos {
+                break;
+            }
+            let end = match self.tokens.get(self.pos + 1) {
+                Some(&(j, _)) => j,
+                None => match t {
+                    Literal(len) => i + len,
+                    LeftBraceBrace | RightBraceBrace => i + 2,
+                    _ => i + 1,
+                },
+            };
+            if end > pos {
+                self.tokens[self.pos] = match t {
+                    Space => (pos, Space),
+                    _ => (pos, Literal(end - pos)),
+                };
+                break;
+            }
+            self.pos += 1;
+        }
+    }
 }
 
 impl Iterator for Stream {